@@ -0,0 +1,83 @@
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::io::sink;
+use std::time::{Duration, Instant};
+use tetris::engine::Game;
+use tetris::engine::GameSettings;
+use tetris::engine::Input;
+use tetris::render::Screen;
+
+use crate::game_view::GameView;
+use crate::ScreenSetting;
+
+//How many frames a random move is held before the next one is picked
+const MOVE_FRAMES: u64 = 12;
+
+//Plays random inputs without a terminal, a new game starts whenever one ends.
+//Every frame is also drawn as the game would, to count what it would send to a terminal.
+pub fn run(
+    tetromino: Vec<String>,
+    mut settings: GameSettings,
+    screen_settings: &ScreenSetting,
+    frames: u64,
+) {
+    let mut rng = ChaCha8Rng::seed_from_u64(settings.seed);
+    let mut game = Game::new(tetromino.clone(), &settings);
+    let mut view = GameView::new(&game);
+    let mut screen = Screen::new(
+        screen_settings.screen_width as u16,
+        screen_settings.screen_height as u16,
+    );
+    let mut bytes: u64 = 0;
+    let mut engine_time = Duration::ZERO;
+    let mut input = Input::new();
+    let mut games = 1;
+    let mut pieces: u64 = 0;
+    let mut lines: u64 = 0;
+    let start = Instant::now();
+    for frame in 0..frames {
+        if frame % MOVE_FRAMES == 0 {
+            let direction = rng.gen_range(0..3);
+            input.left = direction == 1;
+            input.right = direction == 2;
+            input.down = rng.gen_bool(0.2);
+        }
+        input.rotate = rng.gen_bool(0.05);
+        input.rotate_counter_clockwise = rng.gen_bool(0.02);
+        input.hold = rng.gen_bool(0.005);
+        input.hard_drop = rng.gen_bool(0.03);
+        let step_start = Instant::now();
+        game.step(&input);
+        engine_time += step_start.elapsed();
+        screen.begin_render();
+        view.render(&mut screen, &game, screen_settings.show_ghost, &[]);
+        //A sink never fails
+        let _ = screen.end_render_to(&mut sink());
+        bytes += screen.bytes_written() as u64;
+        if game.is_game_over() {
+            pieces += game.pieces_placed() as u64;
+            lines += game.lines_cleared() as u64;
+            games += 1;
+            settings.seed = rng.gen();
+            game = Game::new(tetromino.clone(), &settings);
+            view = GameView::new(&game);
+        }
+    }
+    let seconds = engine_time.as_secs_f64();
+    let total_seconds = start.elapsed().as_secs_f64();
+    pieces += game.pieces_placed() as u64;
+    lines += game.lines_cleared() as u64;
+    println!(
+        "{} frames in {:.3}s, {:.0} frames per second",
+        frames,
+        seconds,
+        frames as f64 / seconds.max(f64::EPSILON)
+    );
+    println!("{} games, {} pieces, {} lines", games, pieces, lines);
+    println!(
+        "{:.1} bytes per frame sent to the terminal, {:.3}s with drawing",
+        bytes as f64 / frames.max(1) as f64,
+        total_seconds
+    );
+}
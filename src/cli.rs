@@ -0,0 +1,170 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use tetris::engine::GameMode;
+use tetris::engine::GameSettings;
+use tetris::engine::RandomizerKind;
+
+use crate::config::{COLUMNS, ROWS, START_LEVEL, WALLS_X, WALLS_Y};
+use crate::config::{LOCK_DELAY_MS, SHIFT_MS, SOFT_DROP_MS};
+use crate::game_view::format_time;
+use crate::high_scores::HighScores;
+use crate::ScreenSetting;
+
+//Options given here win over the config file but are not saved to it
+#[derive(Parser)]
+#[command(name = "termtris", version, about = "Tetris in the terminal")]
+pub struct Cli {
+    /// Start this mode right away instead of showing the main menu
+    #[arg(long, value_parser = parse_mode)]
+    pub mode: Option<GameMode>,
+    /// Seed for the piece order, kept when a game is restarted
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Columns of the field
+    #[arg(long, value_parser = clap::value_parser!(i16).range(COLUMNS))]
+    pub width: Option<i16>,
+    /// Rows of the field
+    #[arg(long, value_parser = clap::value_parser!(i16).range(ROWS))]
+    pub height: Option<i16>,
+    /// Level to start on
+    #[arg(long, value_parser = clap::value_parser!(u32).range(START_LEVEL))]
+    pub level: Option<u32>,
+    /// How the next pieces are picked
+    #[arg(long, value_parser = parse_randomizer)]
+    pub randomizer: Option<RandomizerKind>,
+    /// Read and save the config at this path instead of the default one
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Draw with plain ASCII characters
+    #[arg(long)]
+    pub ascii: bool,
+    /// Leave the colors to the terminal
+    #[arg(long)]
+    pub no_color: bool,
+    /// Play back a recorded game, every game is recorded to the replays directory next to
+    /// the high scores. Space pauses, + and - change the speed, . steps a frame while paused
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+    /// Lock delay in milliseconds
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u32).range(LOCK_DELAY_MS))]
+    pub lock_delay: Option<u32>,
+    /// Delayed auto shift in milliseconds
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u32).range(SHIFT_MS))]
+    pub das: Option<u32>,
+    /// Auto repeat rate in milliseconds
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u32).range(SHIFT_MS))]
+    pub arr: Option<u32>,
+    /// Time per row while soft dropping in milliseconds
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u32).range(SOFT_DROP_MS))]
+    pub soft_drop: Option<u32>,
+    /// Hide the ghost piece
+    #[arg(long)]
+    pub no_ghost: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print the high score tables
+    Scores {
+        /// Only print the table of this mode
+        #[arg(long, value_parser = parse_mode)]
+        mode: Option<GameMode>,
+    },
+    /// Run the engine without a terminal and report how fast it is
+    Bench {
+        /// Frames to simulate
+        #[arg(long, default_value_t = 600_000)]
+        frames: u64,
+    },
+}
+
+impl Cli {
+    pub fn apply(&self, game_settings: &mut GameSettings, screen_settings: &mut ScreenSetting) {
+        if let Some(mode) = self.mode {
+            game_settings.mode = mode;
+        }
+        if let Some(seed) = self.seed {
+            game_settings.seed = seed;
+            screen_settings.fixed_seed = true;
+        }
+        if let Some(width) = self.width {
+            game_settings.field_width = width + WALLS_X;
+        }
+        if let Some(height) = self.height {
+            game_settings.field_height = height + WALLS_Y;
+        }
+        if let Some(level) = self.level {
+            game_settings.start_level = level;
+        }
+        if let Some(randomizer) = self.randomizer {
+            game_settings.randomizer = randomizer;
+        }
+        if let Some(lock_delay) = self.lock_delay {
+            game_settings.lock_delay_ms = lock_delay;
+        }
+        if let Some(das) = self.das {
+            game_settings.das_ms = das;
+        }
+        if let Some(arr) = self.arr {
+            game_settings.arr_ms = arr;
+        }
+        if let Some(soft_drop) = self.soft_drop {
+            game_settings.soft_drop_ms = soft_drop;
+        }
+        if self.no_ghost {
+            screen_settings.show_ghost = false;
+        }
+    }
+}
+
+fn parse_mode(name: &str) -> Result<GameMode, String> {
+    GameMode::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = GameMode::ALL.iter().map(|mode| mode.name()).collect();
+        format!("expected one of: {}", names.join(", "))
+    })
+}
+
+fn parse_randomizer(name: &str) -> Result<RandomizerKind, String> {
+    RandomizerKind::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = RandomizerKind::ALL.iter().map(|kind| kind.name()).collect();
+        format!("expected one of: {}", names.join(", "))
+    })
+}
+
+//Plain text for scripts, Sprint is ranked by time so its tables lead with it
+pub fn print_scores(high_scores: &HighScores, mode: Option<GameMode>) {
+    let modes = match mode {
+        Some(mode) => vec![mode],
+        None => GameMode::ALL.to_vec(),
+    };
+    for (index, mode) in modes.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!("{}", mode.name().to_uppercase());
+        let entries = high_scores.entries(*mode);
+        if entries.is_empty() {
+            println!("No scores yet");
+        }
+        for (rank, entry) in entries.iter().enumerate() {
+            let result = if mode.line_goal().is_some() {
+                format_time(entry.frames)
+            } else {
+                entry.score.to_string()
+            };
+            println!(
+                "{:>2} {:<3} {:>11} lines {:>3} level {:>2} time {:>8} {} seed {}",
+                rank + 1,
+                entry.name,
+                result,
+                entry.lines,
+                entry.level,
+                format_time(entry.frames),
+                entry.date,
+                entry.seed
+            );
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use tetris::engine::GameSettings;
+use tetris::engine::RandomizerKind;
+use tetris::engine::MAX_PREVIEW;
+
+use crate::controls::{Action, Controls, Preset};
+use crate::keys::KeyBinding;
+use crate::storage::write_atomically;
+use crate::ScreenSetting;
+
+//The playfield is surrounded by a wall on the sides and the bottom
+pub const WALLS_X: i16 = 2;
+pub const WALLS_Y: i16 = 1;
+//Shared with the command line options
+pub const COLUMNS: RangeInclusive<i64> = 4..=30;
+pub const ROWS: RangeInclusive<i64> = 10..=40;
+pub const START_LEVEL: RangeInclusive<i64> = 1..=30;
+pub const LOCK_DELAY_MS: RangeInclusive<i64> = 0..=5000;
+pub const LINE_CLEAR_DELAY_MS: RangeInclusive<i64> = 0..=5000;
+pub const SHIFT_MS: RangeInclusive<i64> = 0..=1000;
+pub const SOFT_DROP_MS: RangeInclusive<i64> = 0..=1000;
+const PIECE_NAMES: [&str; 7] = ["i", "z", "s", "l", "j", "o", "t"];
+
+//Mirrors config.toml, every section and key is optional
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    pub gameplay: GameplayConfig,
+    pub timing: TimingConfig,
+    pub controls: ControlsConfig,
+    pub colors: ColorsConfig,
+    //Where it was read from and is saved to
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct GameplayConfig {
+    pub columns: i16,
+    pub rows: i16,
+    pub preview: usize,
+    pub randomizer: String,
+    pub start_level: u32,
+    pub ghost: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct TimingConfig {
+    pub lock_delay_ms: u32,
+    pub line_clear_delay_ms: u32,
+    pub das_ms: u32,
+    pub arr_ms: u32,
+    pub soft_drop_ms: u32,
+}
+
+//A preset and the actions whose keys differ from it, the action names are checked by hand
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlsConfig {
+    pub preset: String,
+    #[serde(flatten)]
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ColorsConfig {
+    pub i: String,
+    pub z: String,
+    pub s: String,
+    pub l: String,
+    pub j: String,
+    pub o: String,
+    pub t: String,
+}
+
+impl Config {
+    //Defaults when the default file is missing, a file asked for by path has to exist
+    pub fn load(path: Option<PathBuf>) -> Result<Config, String> {
+        let required = path.is_some();
+        let path = match path.or_else(config_path) {
+            Some(path) => path,
+            None => return Ok(Config::default()),
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound && !required => {
+                return Ok(Config {
+                    path: Some(path),
+                    ..Config::default()
+                })
+            }
+            Err(error) => return Err(format!("{}: {}", path.display(), error)),
+        };
+        let mut config: Config =
+            toml::from_str(&text).map_err(|error| format!("{}: {}", path.display(), error))?;
+        config.path = Some(path);
+        Ok(config)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| String::from("no config directory"))?;
+        let text = toml::to_string(self).map_err(|error| error.to_string())?;
+        write_atomically(path, &text).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    //Checks every value before any of them is used
+    pub fn apply(
+        &self,
+        game_settings: &mut GameSettings,
+        screen_settings: &mut ScreenSetting,
+        controls: &mut Controls,
+    ) -> Result<(), String> {
+        let gameplay = &self.gameplay;
+        check_range("gameplay.columns", gameplay.columns as i64, COLUMNS)?;
+        check_range("gameplay.rows", gameplay.rows as i64, ROWS)?;
+        check_range(
+            "gameplay.preview",
+            gameplay.preview as i64,
+            1..=MAX_PREVIEW as i64,
+        )?;
+        check_range(
+            "gameplay.start_level",
+            gameplay.start_level as i64,
+            START_LEVEL,
+        )?;
+        let randomizer = RandomizerKind::from_name(&gameplay.randomizer).ok_or_else(|| {
+            let names: Vec<&str> = RandomizerKind::ALL.iter().map(|kind| kind.name()).collect();
+            format!(
+                "gameplay.randomizer: unknown randomizer '{}', expected one of: {}",
+                gameplay.randomizer,
+                names.join(", ")
+            )
+        })?;
+        let timing = &self.timing;
+        check_range(
+            "timing.lock_delay_ms",
+            timing.lock_delay_ms as i64,
+            LOCK_DELAY_MS,
+        )?;
+        check_range(
+            "timing.line_clear_delay_ms",
+            timing.line_clear_delay_ms as i64,
+            LINE_CLEAR_DELAY_MS,
+        )?;
+        check_range("timing.das_ms", timing.das_ms as i64, SHIFT_MS)?;
+        check_range("timing.arr_ms", timing.arr_ms as i64, SHIFT_MS)?;
+        check_range(
+            "timing.soft_drop_ms",
+            timing.soft_drop_ms as i64,
+            SOFT_DROP_MS,
+        )?;
+        let new_controls = self.controls.to_controls()?;
+        let mut piece_colors = game_settings.piece_colors;
+        for (index, name) in self.colors.names().iter().enumerate() {
+            piece_colors[index] = parse_color(name).ok_or_else(|| {
+                format!(
+                    "colors.{}: unknown color '{}', expected a name like dark_red or #rrggbb",
+                    PIECE_NAMES[index], name
+                )
+            })?;
+        }
+
+        game_settings.field_width = gameplay.columns + WALLS_X;
+        game_settings.field_height = gameplay.rows + WALLS_Y;
+        game_settings.preview_count = gameplay.preview;
+        game_settings.randomizer = randomizer;
+        game_settings.start_level = gameplay.start_level;
+        game_settings.lock_delay_ms = timing.lock_delay_ms;
+        game_settings.line_clear_delay_ms = timing.line_clear_delay_ms;
+        game_settings.das_ms = timing.das_ms;
+        game_settings.arr_ms = timing.arr_ms;
+        game_settings.soft_drop_ms = timing.soft_drop_ms;
+        game_settings.piece_colors = piece_colors;
+        screen_settings.show_ghost = gameplay.ghost;
+        *controls = new_controls;
+        Ok(())
+    }
+
+    pub fn preset(&self) -> Preset {
+        Preset::from_name(&self.controls.preset).unwrap_or(Preset::Guideline)
+    }
+
+    //Only the actions that differ from the preset are written out
+    pub fn update_controls(&mut self, preset: Preset, controls: &Controls) {
+        let base = Controls::from_preset(preset);
+        self.controls.preset = String::from(preset.name());
+        self.controls.keys = Action::ALL
+            .iter()
+            .filter(|action| controls.keys(**action) != base.keys(**action))
+            .map(|action| {
+                let names = controls
+                    .keys(*action)
+                    .iter()
+                    .map(|key| key.name())
+                    .collect();
+                (String::from(action.name()), names)
+            })
+            .collect();
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let game_settings = GameSettings::new(12, 18);
+        let screen_settings = ScreenSetting::new(34, 20);
+        Config {
+            gameplay: GameplayConfig::from_settings(&game_settings, &screen_settings),
+            timing: TimingConfig::from_settings(&game_settings),
+            controls: ControlsConfig::default(),
+            colors: ColorsConfig::from_colors(&game_settings.piece_colors),
+            path: config_path(),
+        }
+    }
+}
+
+impl GameplayConfig {
+    fn from_settings(
+        game_settings: &GameSettings,
+        screen_settings: &ScreenSetting,
+    ) -> GameplayConfig {
+        GameplayConfig {
+            columns: game_settings.field_width - WALLS_X,
+            rows: game_settings.field_height - WALLS_Y,
+            preview: game_settings.preview_count,
+            randomizer: String::from(game_settings.randomizer.name()),
+            start_level: game_settings.start_level,
+            ghost: screen_settings.show_ghost,
+        }
+    }
+}
+
+impl Default for GameplayConfig {
+    fn default() -> GameplayConfig {
+        Config::default().gameplay
+    }
+}
+
+impl TimingConfig {
+    fn from_settings(game_settings: &GameSettings) -> TimingConfig {
+        TimingConfig {
+            lock_delay_ms: game_settings.lock_delay_ms,
+            line_clear_delay_ms: game_settings.line_clear_delay_ms,
+            das_ms: game_settings.das_ms,
+            arr_ms: game_settings.arr_ms,
+            soft_drop_ms: game_settings.soft_drop_ms,
+        }
+    }
+}
+
+impl Default for TimingConfig {
+    fn default() -> TimingConfig {
+        Config::default().timing
+    }
+}
+
+impl ControlsConfig {
+    //A key may only belong to one action, even one the preset gave it
+    fn to_controls(&self) -> Result<Controls, String> {
+        let preset = Preset::from_name(&self.preset).ok_or_else(|| {
+            let names: Vec<&str> = Preset::ALL.iter().map(|preset| preset.name()).collect();
+            format!(
+                "controls.preset: unknown preset '{}', expected one of: {}",
+                self.preset,
+                names.join(", ")
+            )
+        })?;
+        let mut controls = Controls::from_preset(preset);
+        for (name, key_names) in self.keys.iter() {
+            let action = Action::from_name(name)
+                .ok_or_else(|| format!("controls.{}: unknown action", name))?;
+            let mut keys = Vec::new();
+            for key_name in key_names {
+                let key = KeyBinding::parse(key_name)
+                    .ok_or_else(|| format!("controls.{}: unknown key '{}'", name, key_name))?;
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+            controls.set_keys(action, keys);
+        }
+        for (index, action) in Action::ALL.iter().enumerate() {
+            for key in controls.keys(*action) {
+                if let Some(other) = Action::ALL[..index]
+                    .iter()
+                    .find(|other| controls.keys(**other).contains(key))
+                {
+                    return Err(format!(
+                        "controls.{}: {} is already bound to {}",
+                        action.name(),
+                        key.name(),
+                        other.name()
+                    ));
+                }
+            }
+        }
+        Ok(controls)
+    }
+}
+
+impl Default for ControlsConfig {
+    fn default() -> ControlsConfig {
+        ControlsConfig {
+            preset: String::from(Preset::Guideline.name()),
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl ColorsConfig {
+    fn from_colors(colors: &[Color]) -> ColorsConfig {
+        ColorsConfig {
+            i: color_name(colors[0]),
+            z: color_name(colors[1]),
+            s: color_name(colors[2]),
+            l: color_name(colors[3]),
+            j: color_name(colors[4]),
+            o: color_name(colors[5]),
+            t: color_name(colors[6]),
+        }
+    }
+
+    //In piece order
+    fn names(&self) -> [&String; 7] {
+        [
+            &self.i, &self.z, &self.s, &self.l, &self.j, &self.o, &self.t,
+        ]
+    }
+}
+
+impl Default for ColorsConfig {
+    fn default() -> ColorsConfig {
+        Config::default().colors
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|directory| directory.join("termtris").join("config.toml"))
+}
+
+fn check_range(name: &str, value: i64, range: RangeInclusive<i64>) -> Result<(), String> {
+    if !range.contains(&value) {
+        return Err(format!(
+            "{}: {} is out of range, expected {} to {}",
+            name,
+            value,
+            range.start(),
+            range.end()
+        ));
+    }
+    Ok(())
+}
+
+const COLOR_NAMES: [(&str, Color); 16] = [
+    ("black", Color::Black),
+    ("dark_grey", Color::DarkGrey),
+    ("red", Color::Red),
+    ("dark_red", Color::DarkRed),
+    ("green", Color::Green),
+    ("dark_green", Color::DarkGreen),
+    ("yellow", Color::Yellow),
+    ("dark_yellow", Color::DarkYellow),
+    ("blue", Color::Blue),
+    ("dark_blue", Color::DarkBlue),
+    ("magenta", Color::Magenta),
+    ("dark_magenta", Color::DarkMagenta),
+    ("cyan", Color::Cyan),
+    ("dark_cyan", Color::DarkCyan),
+    ("white", Color::White),
+    ("grey", Color::Grey),
+];
+
+//One of the 16 terminal color names or #rrggbb
+pub fn parse_color(name: &str) -> Option<Color> {
+    if let Some(hex) = name.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|digit| digit.is_ascii_hexdigit()) {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Color::Rgb {
+            r: (value >> 16) as u8,
+            g: (value >> 8) as u8,
+            b: value as u8,
+        });
+    }
+    COLOR_NAMES
+        .iter()
+        .find(|(color_name, _)| color_name.eq_ignore_ascii_case(name))
+        .map(|(_, color)| *color)
+}
+
+pub fn color_name(color: Color) -> String {
+    if let Color::Rgb { r, g, b } = color {
+        return format!("#{:02x}{:02x}{:02x}", r, g, b);
+    }
+    COLOR_NAMES
+        .iter()
+        .find(|(_, other)| *other == color)
+        .map(|(name, _)| String::from(*name))
+        .unwrap_or_else(|| String::from("white"))
+}
@@ -0,0 +1,220 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::keys::KeyBinding;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    SonicDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    RotateHalfTurn,
+    Hold,
+    Pause,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::SonicDrop,
+        Action::RotateClockwise,
+        Action::RotateCounterClockwise,
+        Action::RotateHalfTurn,
+        Action::Hold,
+        Action::Pause,
+        Action::Quit,
+    ];
+
+    //The key in the controls section of the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::SoftDrop => "soft_drop",
+            Action::HardDrop => "hard_drop",
+            Action::SonicDrop => "sonic_drop",
+            Action::RotateClockwise => "rotate_clockwise",
+            Action::RotateCounterClockwise => "rotate_counter_clockwise",
+            Action::RotateHalfTurn => "rotate_half_turn",
+            Action::Hold => "hold",
+            Action::Pause => "pause",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::SoftDrop => "Soft drop",
+            Action::HardDrop => "Hard drop",
+            Action::SonicDrop => "Sonic drop",
+            Action::RotateClockwise => "Rotate",
+            Action::RotateCounterClockwise => "Rotate left",
+            Action::RotateHalfTurn => "Rotate 180",
+            Action::Hold => "Hold",
+            Action::Pause => "Pause",
+            Action::Quit => "Quit",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Preset {
+    Guideline,
+    Vim,
+    Wasd,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Guideline, Preset::Vim, Preset::Wasd];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Guideline => "guideline",
+            Preset::Vim => "vim",
+            Preset::Wasd => "wasd",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        Preset::ALL
+            .iter()
+            .copied()
+            .find(|preset| preset.name().eq_ignore_ascii_case(name))
+    }
+
+    //Keys in the same order as Action::ALL
+    fn keys(&self) -> [&'static [KeyCode]; 11] {
+        match self {
+            Preset::Guideline => [
+                &[KeyCode::Left],
+                &[KeyCode::Right],
+                &[KeyCode::Down],
+                &[KeyCode::Char(' ')],
+                &[KeyCode::Char('s')],
+                &[KeyCode::Up, KeyCode::Char('x')],
+                &[KeyCode::Char('z')],
+                &[KeyCode::Char('a')],
+                &[KeyCode::Char('c')],
+                &[KeyCode::Esc, KeyCode::Char('p')],
+                &[KeyCode::Char('q')],
+            ],
+            Preset::Vim => [
+                &[KeyCode::Char('h')],
+                &[KeyCode::Char('l')],
+                &[KeyCode::Char('j')],
+                &[KeyCode::Char(' ')],
+                &[KeyCode::Char('g')],
+                &[KeyCode::Char('k')],
+                &[KeyCode::Char('d')],
+                &[KeyCode::Char('f')],
+                &[KeyCode::Char('c')],
+                &[KeyCode::Esc, KeyCode::Char('p')],
+                &[KeyCode::Char('q')],
+            ],
+            Preset::Wasd => [
+                &[KeyCode::Char('a')],
+                &[KeyCode::Char('d')],
+                &[KeyCode::Char('s')],
+                &[KeyCode::Char('w')],
+                &[KeyCode::Char('e')],
+                &[KeyCode::Char('k')],
+                &[KeyCode::Char('j')],
+                &[KeyCode::Char('l')],
+                &[KeyCode::Char(' ')],
+                &[KeyCode::Esc, KeyCode::Char('p')],
+                &[KeyCode::Char('q')],
+            ],
+        }
+    }
+}
+
+//Every key that triggers each action, any of them will do and a key belongs to one action only
+#[derive(Clone, PartialEq)]
+pub struct Controls {
+    bindings: Vec<Vec<KeyBinding>>,
+}
+
+impl Controls {
+    pub fn from_preset(preset: Preset) -> Controls {
+        let bindings = preset
+            .keys()
+            .iter()
+            .map(|codes| {
+                codes
+                    .iter()
+                    .map(|code| KeyBinding::new(*code, KeyModifiers::NONE))
+                    .collect()
+            })
+            .collect();
+        Controls { bindings }
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        &self.bindings[action as usize]
+    }
+
+    pub fn set_keys(&mut self, action: Action, keys: Vec<KeyBinding>) {
+        self.bindings[action as usize] = keys;
+    }
+
+    //The action a key already belongs to
+    pub fn action_for(&self, key: KeyBinding) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| self.keys(*action).contains(&key))
+    }
+
+    //Bound keys work in menus too, moving maps to the arrows, rotating to up,
+    //soft drop to down and hard drop to select. Other keys are left as they are.
+    pub fn menu_key(&self, event: &KeyEvent) -> KeyCode {
+        match self.action_for(KeyBinding::from_event(event)) {
+            Some(Action::MoveLeft) => KeyCode::Left,
+            Some(Action::MoveRight) => KeyCode::Right,
+            Some(Action::SoftDrop) => KeyCode::Down,
+            Some(Action::HardDrop) => KeyCode::Enter,
+            Some(Action::RotateClockwise)
+            | Some(Action::RotateCounterClockwise)
+            | Some(Action::RotateHalfTurn) => KeyCode::Up,
+            _ => event.code,
+        }
+    }
+
+    pub fn matches(&self, action: Action, key: KeyBinding) -> bool {
+        self.keys(action).contains(&key)
+    }
+
+    //Adds the key to the action and takes it away from any other action
+    pub fn bind(&mut self, action: Action, key: KeyBinding) {
+        for keys in self.bindings.iter_mut() {
+            keys.retain(|other| *other != key);
+        }
+        self.bindings[action as usize].push(key);
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.bindings[action as usize].clear();
+    }
+}
+
+impl Default for Controls {
+    fn default() -> Controls {
+        Controls::from_preset(Preset::Guideline)
+    }
+}
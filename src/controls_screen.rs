@@ -0,0 +1,155 @@
+use crossterm::{event::KeyCode, style::Color};
+use std::io::Result;
+use tetris::render::Frame;
+use tetris::render::FrameStyle;
+use tetris::render::Screen;
+use tetris::render::Text;
+
+use crate::config::Config;
+use crate::controls::{Action, Controls, Preset};
+use crate::keys::KeyBinding;
+use crate::menu::Menu;
+use crate::ui;
+use crate::ScreenSetting;
+
+const PRESET: usize = 0;
+const BACK: usize = Action::ALL.len() + 1;
+const KEYS_WIDTH: usize = 15;
+
+//What the screen is waiting for after an action was chosen
+enum Binding {
+    None,
+    WaitingForKey(Action),
+    Conflict(Action, KeyBinding, Action),
+}
+
+//Enter waits for a key to add to the selected action, Delete clears it and the
+//preset entry swaps all keys at once. Every change is written to the config file.
+pub fn controls_screen(
+    screen: &mut Screen,
+    screen_settings: &ScreenSetting,
+    controls: &mut Controls,
+    config: &mut Config,
+) -> Result<()> {
+    let frame = Frame::new(32, 18, FrameStyle::DoubleLine, Color::White, Color::Black);
+    let title = Text::new(String::from(" CONTROLS "), Color::Cyan, Color::Black);
+    let mut menu = Menu::new(vec![String::new(); BACK + 1]);
+    menu.set_entry(BACK, String::from("Back"));
+    let mut preset = config.preset();
+    let mut binding = Binding::None;
+    let mut status: Option<(String, Color)> = None;
+    loop {
+        menu.set_entry(PRESET, format!("{:<11}{:>15}", "Preset", preset.name()));
+        for (index, action) in Action::ALL.iter().enumerate() {
+            menu.set_entry(index + 1, action_entry(*action, controls));
+        }
+        let (hint, hint_color) = match &binding {
+            Binding::None => (String::from("Enter add key, Del clear"), Color::DarkGrey),
+            Binding::WaitingForKey(action) => {
+                (format!("Press a key for {}", action.label()), Color::Yellow)
+            }
+            Binding::Conflict(_, key, other) => (
+                format!("{} is used by {}", key.name(), other.label()),
+                Color::Red,
+            ),
+        };
+        let (detail, detail_color) = match (&binding, &status) {
+            (Binding::WaitingForKey(_), _) => (String::from("Esc to cancel"), Color::DarkGrey),
+            (Binding::Conflict(..), _) => (
+                String::from("Enter to move it, Esc to keep"),
+                Color::DarkGrey,
+            ),
+            (Binding::None, Some((message, color))) => (message.clone(), *color),
+            (Binding::None, None) => (String::new(), Color::DarkGrey),
+        };
+        ui::show(screen, screen_settings, |screen| {
+            screen.add_element_at(&frame, 1, 1);
+            screen.add_element_at(&title, 12, 1);
+            menu.render(screen, 3, 3);
+            screen.add_string_at(hint, hint_color, Color::Black, 3, 16);
+            screen.add_string_at(detail, detail_color, Color::Black, 3, 17);
+        })?;
+        let event = match ui::next_key_event(screen)? {
+            Some(event) => event,
+            None => continue,
+        };
+        match binding {
+            Binding::WaitingForKey(action) => {
+                binding = Binding::None;
+                if event.code == KeyCode::Esc {
+                    continue;
+                }
+                let key = KeyBinding::from_event(&event);
+                match controls.action_for(key) {
+                    Some(other) if other == action => continue,
+                    Some(other) => {
+                        binding = Binding::Conflict(action, key, other);
+                        continue;
+                    }
+                    None => controls.bind(action, key),
+                }
+            }
+            Binding::Conflict(action, key, _) => {
+                binding = Binding::None;
+                if event.code != KeyCode::Enter {
+                    continue;
+                }
+                controls.bind(action, key);
+            }
+            Binding::None => {
+                let selected = menu.selected();
+                let code = controls.menu_key(&event);
+                match code {
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Left | KeyCode::Right if selected == PRESET => {
+                        let direction = if code == KeyCode::Left { -1 } else { 1 };
+                        preset = step_preset(preset, direction);
+                        *controls = Controls::from_preset(preset);
+                    }
+                    KeyCode::Delete | KeyCode::Backspace
+                        if selected != PRESET && selected != BACK =>
+                    {
+                        controls.clear(Action::ALL[selected - 1]);
+                    }
+                    _ if menu.handle(&event, controls) => match selected {
+                        PRESET => {
+                            preset = step_preset(preset, 1);
+                            *controls = Controls::from_preset(preset);
+                        }
+                        BACK => return Ok(()),
+                        _ => {
+                            binding = Binding::WaitingForKey(Action::ALL[selected - 1]);
+                            continue;
+                        }
+                    },
+                    _ => continue,
+                }
+            }
+        }
+        config.update_controls(preset, controls);
+        status = Some(match config.save() {
+            Ok(()) => (String::from("Saved to config.toml"), Color::DarkGrey),
+            Err(message) => (message, Color::Red),
+        });
+    }
+}
+
+//Keys that do not fit are cut off with a trailing ~
+fn action_entry(action: Action, controls: &Controls) -> String {
+    let names: Vec<String> = controls.keys(action).iter().map(|key| key.name()).collect();
+    let mut keys = names.join(" ");
+    if keys.chars().count() > KEYS_WIDTH {
+        keys = keys.chars().take(KEYS_WIDTH - 1).collect::<String>() + "~";
+    }
+    format!("{:<11}{:>15}", action.label(), keys)
+}
+
+fn step_preset(preset: Preset, direction: i32) -> Preset {
+    let presets = Preset::ALL;
+    let current = presets
+        .iter()
+        .position(|other| *other == preset)
+        .unwrap_or(0);
+    let next = (current as i32 + direction).rem_euclid(presets.len() as i32);
+    presets[next as usize]
+}
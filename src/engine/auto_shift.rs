@@ -0,0 +1,149 @@
+//Delayed Auto Shift and Auto Repeat Rate for sideways movement. The newest held
+//direction wins, a tap moves one cell and holding past the delay repeats the move.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AutoShift {
+    pub das_frames: u32,
+    pub arr_frames: u32,
+    pub direction: i16,
+    pub timer: u32,
+    pub left_held: bool,
+    pub right_held: bool,
+}
+
+pub struct Shift {
+    pub direction: i16,
+    pub cells: u32,
+}
+
+impl AutoShift {
+    pub fn new(das_frames: u32, arr_frames: u32) -> AutoShift {
+        AutoShift {
+            das_frames,
+            arr_frames,
+            direction: 0,
+            timer: 0,
+            left_held: false,
+            right_held: false,
+        }
+    }
+
+    //Called once per frame with the held state of both keys. A zero repeat rate
+    //shifts as far as the piece can go, which the caller handles by stopping when blocked.
+    pub fn update(&mut self, left: bool, right: bool) -> Shift {
+        let left_pressed = left && !self.left_held;
+        let right_pressed = right && !self.right_held;
+        self.left_held = left;
+        self.right_held = right;
+        let direction = if left_pressed {
+            -1
+        } else if right_pressed {
+            1
+        } else if (self.direction == -1 && left) || (self.direction == 1 && right) {
+            self.direction
+        } else if left {
+            -1
+        } else if right {
+            1
+        } else {
+            0
+        };
+        if direction == 0 {
+            self.direction = 0;
+            return Shift {
+                direction,
+                cells: 0,
+            };
+        }
+        if direction != self.direction {
+            self.direction = direction;
+            self.timer = 0;
+            return Shift {
+                direction,
+                cells: 1,
+            };
+        }
+        self.timer += 1;
+        let cells = if self.timer < self.das_frames {
+            0
+        } else if self.arr_frames == 0 {
+            u32::MAX
+        } else if (self.timer - self.das_frames).is_multiple_of(self.arr_frames) {
+            1
+        } else {
+            0
+        };
+        Shift { direction, cells }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //The cells moved on every frame, negative to the left
+    fn shifts(auto_shift: &mut AutoShift, held: &[(bool, bool)]) -> Vec<i64> {
+        held.iter()
+            .map(|(left, right)| {
+                let shift = auto_shift.update(*left, *right);
+                shift.direction as i64 * shift.cells.min(99) as i64
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_held_key_moves_once_then_repeats_after_the_delay() {
+        let mut auto_shift = AutoShift::new(4, 2);
+        let moved = shifts(&mut auto_shift, &[(false, true); 10]);
+        assert_eq!(moved, [1, 0, 0, 0, 1, 0, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn a_zero_repeat_rate_moves_all_the_way_after_the_delay() {
+        let mut auto_shift = AutoShift::new(3, 0);
+        let moved = shifts(&mut auto_shift, &[(true, false); 5]);
+        assert_eq!(moved, [-1, 0, 0, -99, -99]);
+    }
+
+    #[test]
+    fn letting_go_stops_and_the_next_press_starts_over() {
+        let mut auto_shift = AutoShift::new(2, 1);
+        let held = [
+            (true, false),
+            (true, false),
+            (true, false),
+            (false, false),
+            (true, false),
+            (true, false),
+        ];
+        assert_eq!(shifts(&mut auto_shift, &held), [-1, 0, -1, 0, -1, 0]);
+    }
+
+    #[test]
+    fn the_newest_direction_wins_while_both_are_held() {
+        let mut auto_shift = AutoShift::new(3, 1);
+        let held = [
+            (true, false),
+            (true, false),
+            //Right goes down while left is still held
+            (true, true),
+            (true, true),
+            (true, true),
+            (true, true),
+            //Right is let go, left takes over and charges its delay again
+            (true, false),
+            (true, false),
+            (true, false),
+            (true, false),
+        ];
+        assert_eq!(
+            shifts(&mut auto_shift, &held),
+            [-1, 0, 1, 0, 0, 1, -1, 0, 0, -1]
+        );
+    }
+
+    #[test]
+    fn pressing_both_keys_on_the_same_frame_goes_left() {
+        let mut auto_shift = AutoShift::new(3, 1);
+        assert_eq!(shifts(&mut auto_shift, &[(true, true); 2]), [-1, 0]);
+    }
+}
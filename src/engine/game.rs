@@ -0,0 +1,1221 @@
+use super::auto_shift::AutoShift;
+use super::gravity::gravity_per_frame;
+use super::gravity::lock_delay_percent;
+use super::gravity::LINES_PER_LEVEL;
+use super::gravity::ROW;
+use super::input::Input;
+use super::mode::GameMode;
+use super::randomizer::Randomizer;
+use super::randomizer::RandomizerKind;
+use super::scoring::ScoreEvent;
+use super::scoring::Scoring;
+use super::scoring::SpinKind;
+use super::settings::GameSettings;
+use super::shape::piece_size;
+use super::shape::TetrisShape;
+use super::shape::PIECE_COUNT;
+use super::shape::PIECE_T;
+use super::snapshot::GameSnapshot;
+use crate::render::screen::ScreenElement;
+use crate::render::Block;
+use crate::rotation::Kick;
+use crate::rotation::Rotation;
+use crossterm::style::Color;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+
+pub const MAX_PREVIEW: usize = 6;
+pub const FRAMES_PER_SECOND: u32 = 60;
+pub const MAX_LOCK_RESETS: u32 = 15;
+
+pub enum GameEvent {
+    PieceLocked,
+    PieceHeld,
+    LinesFilled(u16),
+    LinesCleared(u16),
+    LevelUp(u32),
+    Scored(ScoreEvent),
+    GoalReached,
+    GameOver,
+}
+
+//While lines are clearing there is no active piece, the next one spawns when it ends
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameState {
+    Falling,
+    ClearingLines(u32),
+}
+
+pub struct Game {
+    tetromino: Vec<String>,
+    field: Block,
+    field_width: i16,
+    field_height: i16,
+    mode: GameMode,
+    piece: TetrisShape,
+    piece_colors: [Color; PIECE_COUNT as usize],
+    queue: VecDeque<i16>,
+    preview_count: usize,
+    randomizer: Box<dyn Randomizer>,
+    randomizer_kind: RandomizerKind,
+    rng: ChaCha8Rng,
+    seed: u64,
+    lines: Vec<i16>,
+    state: GameState,
+    line_clear_frames: u32,
+    score: u32,
+    scoring: Scoring,
+    start_level: u32,
+    level: u32,
+    lines_cleared: u32,
+    gravity_progress: u64,
+    pieces_placed: u32,
+    frames_played: u64,
+    last_kick: Option<Kick>,
+    lock_delay_ms: u32,
+    auto_shift: AutoShift,
+    soft_drop_frames: u32,
+    soft_drop_timer: u32,
+    soft_drop_held: bool,
+    lock_timer: u32,
+    lock_resets: u32,
+    lowest_y: i16,
+    held_piece: Option<i16>,
+    can_hold: bool,
+    goal_reached: bool,
+    game_over: bool,
+}
+
+impl Game {
+    pub fn new(tetromino: Vec<String>, settings: &GameSettings) -> Game {
+        let field_width = settings.field_width;
+        let field_height = settings.field_height;
+        let preview_count = settings.preview_count.clamp(1, MAX_PREVIEW);
+        let mut randomizer = settings.randomizer.create();
+        //Every random decision comes from this generator so a seed replays the same game
+        let mut rng = ChaCha8Rng::seed_from_u64(settings.seed);
+        let first_piece = randomizer.next_piece(&mut rng);
+        let queue: VecDeque<i16> = (0..preview_count)
+            .map(|_| randomizer.next_piece(&mut rng))
+            .collect();
+        Game {
+            tetromino,
+            field: create_initial_field(field_width, field_height),
+            field_width,
+            field_height,
+            mode: settings.mode,
+            piece: spawn_piece(
+                first_piece,
+                field_width,
+                settings.piece_colors[first_piece as usize],
+            ),
+            piece_colors: settings.piece_colors,
+            queue,
+            preview_count,
+            randomizer,
+            randomizer_kind: settings.randomizer,
+            rng,
+            seed: settings.seed,
+            lines: Vec::new(),
+            state: GameState::Falling,
+            line_clear_frames: ms_to_frames(settings.line_clear_delay_ms),
+            score: 0,
+            scoring: Scoring::new(),
+            start_level: settings.start_level.max(1),
+            level: settings.start_level.max(1),
+            lines_cleared: 0,
+            gravity_progress: 0,
+            pieces_placed: 0,
+            frames_played: 0,
+            last_kick: None,
+            lock_delay_ms: settings.lock_delay_ms,
+            auto_shift: AutoShift::new(
+                ms_to_frames(settings.das_ms),
+                ms_to_frames(settings.arr_ms),
+            ),
+            soft_drop_frames: ms_to_frames(settings.soft_drop_ms).max(1),
+            soft_drop_timer: 0,
+            soft_drop_held: false,
+            lock_timer: 0,
+            lock_resets: 0,
+            lowest_y: 0,
+            held_piece: None,
+            can_hold: true,
+            goal_reached: false,
+            game_over: false,
+        }
+    }
+
+    //Captures the whole state so the game can be saved and continued later
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot {
+            field: self.field.clone(),
+            field_width: self.field_width,
+            field_height: self.field_height,
+            mode: self.mode,
+            piece: self.piece.clone(),
+            piece_colors: self.piece_colors,
+            queue: self.queue.iter().copied().collect(),
+            randomizer: self.randomizer_kind,
+            randomizer_state: self.randomizer.state(),
+            seed: self.seed,
+            rng_word_pos: self.rng.get_word_pos(),
+            lines: self.lines.clone(),
+            state: self.state,
+            line_clear_frames: self.line_clear_frames,
+            score: self.score,
+            scoring: self.scoring.clone(),
+            start_level: self.start_level,
+            level: self.level,
+            lines_cleared: self.lines_cleared,
+            gravity_progress: self.gravity_progress,
+            pieces_placed: self.pieces_placed,
+            frames_played: self.frames_played,
+            last_kick: self.last_kick,
+            lock_delay_ms: self.lock_delay_ms,
+            auto_shift: self.auto_shift.clone(),
+            soft_drop_frames: self.soft_drop_frames,
+            soft_drop_timer: self.soft_drop_timer,
+            soft_drop_held: self.soft_drop_held,
+            lock_timer: self.lock_timer,
+            lock_resets: self.lock_resets,
+            lowest_y: self.lowest_y,
+            held_piece: self.held_piece,
+            can_hold: self.can_hold,
+            goal_reached: self.goal_reached,
+            game_over: self.game_over,
+        }
+    }
+
+    //The snapshot may come from a file, anything the game could trip over is refused
+    pub fn restore(tetromino: Vec<String>, snapshot: GameSnapshot) -> Result<Game, String> {
+        let field_width = snapshot.field_width;
+        let field_height = snapshot.field_height;
+        if field_width < 3
+            || field_height < 2
+            || snapshot.field.get_width() != field_width as u16
+            || snapshot.field.get_height() != field_height as u16
+        {
+            return Err(String::from("the field does not match its size"));
+        }
+        let valid_piece = |piece: &i16| (0..PIECE_COUNT).contains(piece);
+        let pieces_valid = valid_piece(&snapshot.piece.current_piece)
+            && snapshot.queue.iter().all(valid_piece)
+            && snapshot.held_piece.iter().all(valid_piece);
+        if !pieces_valid {
+            return Err(String::from("unknown piece"));
+        }
+        if snapshot.queue.is_empty() || snapshot.queue.len() > MAX_PREVIEW {
+            return Err(String::from("the queue is empty or too long"));
+        }
+        let rows_valid = snapshot
+            .lines
+            .iter()
+            .all(|row| (0..field_height - 1).contains(row));
+        let clearing = matches!(snapshot.state, GameState::ClearingLines(_));
+        if !rows_valid || clearing == snapshot.lines.is_empty() {
+            return Err(String::from(
+                "the lines being cleared do not match the state",
+            ));
+        }
+        if snapshot.start_level == 0 || snapshot.level == 0 || snapshot.soft_drop_frames == 0 {
+            return Err(String::from("levels and timers must be above zero"));
+        }
+        //The queue is kept as long as the preview between pieces
+        let preview_count = snapshot.queue.len();
+        let mut randomizer = snapshot.randomizer.create();
+        randomizer.restore(&snapshot.randomizer_state)?;
+        let mut rng = ChaCha8Rng::seed_from_u64(snapshot.seed);
+        rng.set_word_pos(snapshot.rng_word_pos);
+        let game = Game {
+            tetromino,
+            field: snapshot.field,
+            field_width,
+            field_height,
+            mode: snapshot.mode,
+            piece: snapshot.piece,
+            piece_colors: snapshot.piece_colors,
+            queue: snapshot.queue.into_iter().collect(),
+            preview_count,
+            randomizer,
+            randomizer_kind: snapshot.randomizer,
+            rng,
+            seed: snapshot.seed,
+            lines: snapshot.lines,
+            state: snapshot.state,
+            line_clear_frames: snapshot.line_clear_frames,
+            score: snapshot.score,
+            scoring: snapshot.scoring,
+            start_level: snapshot.start_level,
+            level: snapshot.level,
+            lines_cleared: snapshot.lines_cleared,
+            gravity_progress: snapshot.gravity_progress,
+            pieces_placed: snapshot.pieces_placed,
+            frames_played: snapshot.frames_played,
+            last_kick: snapshot.last_kick,
+            lock_delay_ms: snapshot.lock_delay_ms,
+            auto_shift: snapshot.auto_shift,
+            soft_drop_frames: snapshot.soft_drop_frames,
+            soft_drop_timer: snapshot.soft_drop_timer,
+            soft_drop_held: snapshot.soft_drop_held,
+            lock_timer: snapshot.lock_timer,
+            lock_resets: snapshot.lock_resets,
+            lowest_y: snapshot.lowest_y,
+            held_piece: snapshot.held_piece,
+            can_hold: snapshot.can_hold,
+            goal_reached: snapshot.goal_reached,
+            game_over: snapshot.game_over,
+        };
+        //A falling piece that overlaps the field would be stuck for good
+        let piece = &game.piece;
+        let fits = game.does_piece_fit(
+            piece.current_piece,
+            &piece.current_rotation,
+            piece.current_x,
+            piece.current_y,
+        );
+        if !game.game_over && !clearing && !fits {
+            return Err(String::from("the falling piece overlaps the field"));
+        }
+        Ok(game)
+    }
+
+    //Advances the game by one frame, the caller runs FRAMES_PER_SECOND of them a second
+    pub fn step(&mut self, input: &Input) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        if self.game_over {
+            return events;
+        }
+        self.frames_played += 1;
+        if let Some(limit) = self.mode.time_limit() {
+            if self.frames_played >= limit {
+                self.reach_goal(&mut events);
+                return events;
+            }
+        }
+        if let GameState::ClearingLines(frames_left) = self.state {
+            //Keep following the held keys so DAS stays charged through the delay
+            self.auto_shift.update(input.left, input.right);
+            self.soft_drop_held = input.down;
+            if frames_left > 1 {
+                self.state = GameState::ClearingLines(frames_left - 1);
+                return events;
+            }
+            events.push(GameEvent::LinesCleared(self.lines.len() as u16));
+            self.clear_lines();
+            self.state = GameState::Falling;
+            let next_piece = self.take_next_piece();
+            self.spawn(next_piece, &mut events);
+            return events;
+        }
+        if input.hold && self.can_hold {
+            self.hold_piece(&mut events);
+            //The swapped in piece did not fit, nothing else happens on this frame
+            if self.game_over {
+                return events;
+            }
+        }
+        self.move_shape(input);
+        if input.hard_drop {
+            self.hard_drop(&mut events);
+        } else {
+            self.apply_gravity();
+            self.update_lock_delay(&mut events);
+        }
+        events
+    }
+
+    pub fn tetromino(&self) -> &[String] {
+        &self.tetromino
+    }
+
+    pub fn field(&self) -> &Block {
+        &self.field
+    }
+
+    pub fn piece(&self) -> &TetrisShape {
+        &self.piece
+    }
+
+    pub fn piece_color(&self, p_piece: i16) -> Color {
+        self.piece_colors[p_piece as usize]
+    }
+
+    pub fn field_width(&self) -> i16 {
+        self.field_width
+    }
+
+    pub fn field_height(&self) -> i16 {
+        self.field_height
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn lines_cleared(&self) -> u32 {
+        self.lines_cleared
+    }
+
+    pub fn pieces_placed(&self) -> u32 {
+        self.pieces_placed
+    }
+
+    pub fn frames_played(&self) -> u64 {
+        self.frames_played
+    }
+
+    //The wall kick used by the last successful rotation, cleared by any later movement
+    pub fn last_kick(&self) -> Option<&Kick> {
+        self.last_kick.as_ref()
+    }
+
+    //Upcoming pieces, the front is the next one to spawn
+    pub fn next_pieces(&self) -> &VecDeque<i16> {
+        &self.queue
+    }
+
+    pub fn held_piece(&self) -> Option<i16> {
+        self.held_piece
+    }
+
+    //Holding is allowed once per piece, the next lock frees the slot again
+    pub fn can_hold(&self) -> bool {
+        self.can_hold
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    //False while lines are clearing, the last piece is already part of the field then
+    pub fn has_active_piece(&self) -> bool {
+        self.state == GameState::Falling
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    //Frames left before a timed mode ends
+    pub fn time_left(&self) -> Option<u64> {
+        self.mode
+            .time_limit()
+            .map(|limit| limit.saturating_sub(self.frames_played))
+    }
+
+    //True when the game ended because the goal of the mode was met, not by topping out
+    pub fn is_goal_reached(&self) -> bool {
+        self.goal_reached
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    fn clear_lines(&mut self) {
+        for elem in self.lines.iter().copied() {
+            for px in 1..self.field_width - 1 {
+                self.field
+                    .change_content(px as u16, elem as u16, ' ', Color::Black, Color::Black);
+                for py in (1..elem + 1).rev() {
+                    let index = ((py - 1) * self.field_width + px) as usize;
+                    let new_char = *self.field.get_content_by_index(index);
+                    let new_color = *self.field.get_background_color_by_index(index);
+                    self.field.change_content(
+                        px as u16,
+                        py as u16,
+                        new_char,
+                        Color::Grey,
+                        new_color,
+                    );
+                }
+                self.field
+                    .change_content(px as u16, 0, ' ', Color::Black, Color::Black);
+            }
+        }
+        self.lines.clear();
+    }
+
+    //Gravity builds up in fractions of a row, a resting piece does not store any
+    fn apply_gravity(&mut self) {
+        self.gravity_progress += gravity_per_frame(self.level);
+        while self.gravity_progress >= ROW {
+            self.gravity_progress -= ROW;
+            if !self.move_down() {
+                self.gravity_progress = 0;
+            }
+        }
+    }
+
+    fn move_down(&mut self) -> bool {
+        if self.does_piece_fit(
+            self.piece.current_piece,
+            &self.piece.current_rotation,
+            self.piece.current_x,
+            self.piece.current_y + 1,
+        ) {
+            self.piece.current_y += 1;
+            self.last_kick = None;
+            return true;
+        }
+        false
+    }
+
+    //A grounded piece locks once the delay runs out. Reaching a new lowest row
+    //gives back the move and rotate resets so a piece can always be slid into place.
+    fn update_lock_delay(&mut self, events: &mut Vec<GameEvent>) {
+        if self.piece.current_y > self.lowest_y {
+            self.lowest_y = self.piece.current_y;
+            self.lock_resets = 0;
+        }
+        if self.drop_distance() > 0 {
+            self.lock_timer = 0;
+            return;
+        }
+        self.lock_timer += 1;
+        if self.lock_timer >= self.lock_delay_frames() {
+            self.lock_and_spawn(events);
+        }
+    }
+
+    //Moving or rotating a grounded piece restarts its delay, a limited number of times
+    fn reset_lock_delay(&mut self) {
+        if self.lock_timer > 0 && self.lock_resets < MAX_LOCK_RESETS {
+            self.lock_timer = 0;
+            self.lock_resets += 1;
+        }
+    }
+
+    fn lock_delay_frames(&self) -> u32 {
+        let base_frames = ms_to_frames(self.lock_delay_ms) as u64;
+        (base_frames * lock_delay_percent(self.level) as u64 / 100).max(1) as u32
+    }
+
+    //Hard drops are worth two points for every row the piece falls
+    fn hard_drop(&mut self, events: &mut Vec<GameEvent>) {
+        let distance = self.drop_distance();
+        self.sonic_drop();
+        self.score += 2 * distance as u32;
+        self.lock_and_spawn(events);
+    }
+
+    fn sonic_drop(&mut self) {
+        let distance = self.drop_distance();
+        if distance > 0 {
+            self.piece.current_y += distance;
+            self.last_kick = None;
+        }
+    }
+
+    //How many rows the current piece can fall before it rests on something
+    pub fn drop_distance(&self) -> i16 {
+        let mut distance = 0;
+        while self.does_piece_fit(
+            self.piece.current_piece,
+            &self.piece.current_rotation,
+            self.piece.current_x,
+            self.piece.current_y + distance + 1,
+        ) {
+            distance += 1;
+        }
+        distance
+    }
+
+    fn lock_and_spawn(&mut self, events: &mut Vec<GameEvent>) {
+        let spin = self.detect_spin();
+        self.lock_piece();
+        events.push(GameEvent::PieceLocked);
+
+        self.test_full_lines();
+        if !self.lines.is_empty() {
+            events.push(GameEvent::LinesFilled(self.lines.len() as u16));
+        }
+        let perfect_clear = !self.lines.is_empty() && self.is_perfect_clear();
+        let lines = self.lines.len() as u16;
+        if let Some(score_event) = self.scoring.lock(lines, spin, perfect_clear, self.level) {
+            self.score += score_event.points;
+            events.push(GameEvent::Scored(score_event));
+        }
+        self.lines_cleared += lines as u32;
+        let level = self.start_level + self.lines_cleared / LINES_PER_LEVEL;
+        if self.mode.levels_up() && level > self.level {
+            self.level = level;
+            events.push(GameEvent::LevelUp(level));
+        }
+
+        //new piece and gameover
+        self.can_hold = true;
+        self.pieces_placed += 1;
+        if let Some(goal) = self.mode.line_goal() {
+            if self.lines_cleared >= goal {
+                events.push(GameEvent::LinesCleared(lines));
+                self.clear_lines();
+                self.reach_goal(events);
+                return;
+            }
+        }
+        if !self.lines.is_empty() && self.line_clear_frames > 0 {
+            self.state = GameState::ClearingLines(self.line_clear_frames);
+            return;
+        }
+        if !self.lines.is_empty() {
+            events.push(GameEvent::LinesCleared(lines));
+            self.clear_lines();
+        }
+        let next_piece = self.take_next_piece();
+        self.spawn(next_piece, events);
+    }
+
+    fn reach_goal(&mut self, events: &mut Vec<GameEvent>) {
+        self.goal_reached = true;
+        self.game_over = true;
+        events.push(GameEvent::GoalReached);
+    }
+
+    //3-corner rule: a T piece that rotated into place with three of the four
+    //corners around its center filled. It is a mini unless both corners on the
+    //pointing side are filled or the last kick of the table was needed.
+    fn detect_spin(&self) -> SpinKind {
+        let kick = match &self.last_kick {
+            Some(kick) if self.piece.current_piece == PIECE_T => kick,
+            _ => return SpinKind::None,
+        };
+        let center_x = self.piece.current_x + 1;
+        let center_y = self.piece.current_y + 1;
+        let corners = [(-1, -1), (1, -1), (1, 1), (-1, 1)]
+            .map(|(offset_x, offset_y)| self.is_occupied(center_x + offset_x, center_y + offset_y));
+        if corners.iter().filter(|filled| **filled).count() < 3 {
+            return SpinKind::None;
+        }
+        let (front_left, front_right) = match self.piece.current_rotation {
+            Rotation::R0 => (0, 1),
+            Rotation::R90 => (1, 2),
+            Rotation::R180 => (2, 3),
+            Rotation::R270 => (3, 0),
+        };
+        if (corners[front_left] && corners[front_right]) || kick.test == 4 {
+            SpinKind::Full
+        } else {
+            SpinKind::Mini
+        }
+    }
+
+    fn is_occupied(&self, pos_x: i16, pos_y: i16) -> bool {
+        if pos_x < 0 || pos_y < 0 || pos_x >= self.field_width || pos_y >= self.field_height {
+            return true;
+        }
+        *self
+            .field
+            .get_content_by_index((pos_y * self.field_width + pos_x) as usize)
+            != ' '
+    }
+
+    //True when nothing is left once the filled lines are gone
+    fn is_perfect_clear(&self) -> bool {
+        for py in 0..self.field_height - 1 {
+            if self.lines.contains(&py) {
+                continue;
+            }
+            for px in 1..self.field_width - 1 {
+                if self.is_occupied(px, py) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn hold_piece(&mut self, events: &mut Vec<GameEvent>) {
+        let next_piece = match self.held_piece {
+            Some(held) => held,
+            None => self.take_next_piece(),
+        };
+        self.held_piece = Some(self.piece.current_piece);
+        self.can_hold = false;
+        events.push(GameEvent::PieceHeld);
+        self.spawn(next_piece, events);
+    }
+
+    fn take_next_piece(&mut self) -> i16 {
+        while self.queue.len() <= self.preview_count {
+            let piece = self.randomizer.next_piece(&mut self.rng);
+            self.queue.push_back(piece);
+        }
+        self.queue
+            .pop_front()
+            .expect("the queue is refilled before a piece is taken")
+    }
+
+    fn spawn(&mut self, p_piece: i16, events: &mut Vec<GameEvent>) {
+        let color = self.piece_colors[p_piece as usize];
+        self.piece = spawn_piece(p_piece, self.field_width, color);
+        self.last_kick = None;
+        self.lock_timer = 0;
+        self.lock_resets = 0;
+        self.lowest_y = self.piece.current_y;
+        self.gravity_progress = 0;
+        self.game_over = !self.does_piece_fit(
+            self.piece.current_piece,
+            &self.piece.current_rotation,
+            self.piece.current_x,
+            self.piece.current_y,
+        );
+        if self.game_over {
+            events.push(GameEvent::GameOver);
+        }
+    }
+
+    fn lock_piece(&mut self) {
+        let size = self.piece.size();
+        for px in 0..size {
+            for py in 0..size {
+                let char_as_bytes: u8 = self.tetromino[self.piece.current_piece as usize]
+                    .as_bytes()
+                    [Rotation::rotate(px, py, size, &self.piece.current_rotation) as usize];
+                if char_as_bytes as char == 'X' {
+                    self.field.change_content(
+                        (self.piece.current_x + px) as u16,
+                        (self.piece.current_y + py) as u16,
+                        '0',
+                        Color::Grey,
+                        self.piece.current_color,
+                    );
+                }
+            }
+        }
+    }
+
+    fn test_full_lines(&mut self) {
+        for py in 0..self.piece.size() {
+            let row = self.piece.current_y + py;
+            if row >= 0 && row < self.field_height - 1 {
+                let mut line = true;
+                for px in 1..self.field_width - 1 {
+                    let index = ((self.piece.current_y + py) * self.field_width + px) as usize;
+                    line &= *self.field.get_content_by_index(index) != ' ';
+                }
+                if line {
+                    for px in 1..self.field_width - 1 {
+                        self.field.change_content(
+                            px as u16,
+                            (py + self.piece.current_y) as u16,
+                            '=',
+                            Color::Yellow,
+                            Color::Black,
+                        );
+                    }
+                    self.lines.push(self.piece.current_y + py);
+                }
+            }
+        }
+    }
+
+    //Soft dropping earns a point for every row, gravity does not
+    fn move_shape(&mut self, input_state: &Input) {
+        let shift = self.auto_shift.update(input_state.left, input_state.right);
+        for _ in 0..shift.cells {
+            if !self.does_piece_fit(
+                self.piece.current_piece,
+                &self.piece.current_rotation,
+                self.piece.current_x + shift.direction,
+                self.piece.current_y,
+            ) {
+                break;
+            }
+            self.piece.current_x += shift.direction;
+            self.last_kick = None;
+            self.reset_lock_delay();
+        }
+        if self.soft_drop_step(input_state.down)
+            && self.does_piece_fit(
+                self.piece.current_piece,
+                &self.piece.current_rotation,
+                self.piece.current_x,
+                self.piece.current_y + 1,
+            )
+        {
+            self.piece.current_y += 1;
+            self.last_kick = None;
+            self.score += 1;
+        }
+
+        if input_state.rotate {
+            self.rotate_piece(Rotation::rotate_clockwise(&self.piece.current_rotation));
+        }
+        if input_state.rotate_counter_clockwise {
+            self.rotate_piece(Rotation::rotate_counter_clockwise(
+                &self.piece.current_rotation,
+            ));
+        }
+        if input_state.rotate_half_turn {
+            self.rotate_piece(Rotation::rotate_half_turn(&self.piece.current_rotation));
+        }
+        if input_state.sonic_drop {
+            self.sonic_drop();
+        }
+    }
+
+    //Soft drop moves on the frame the key goes down and then every soft_drop_frames
+    fn soft_drop_step(&mut self, held: bool) -> bool {
+        let pressed = held && !self.soft_drop_held;
+        self.soft_drop_held = held;
+        if !held {
+            return false;
+        }
+        if pressed {
+            self.soft_drop_timer = 0;
+            return true;
+        }
+        self.soft_drop_timer += 1;
+        self.soft_drop_timer.is_multiple_of(self.soft_drop_frames)
+    }
+
+    //Tries every SRS kick for the rotation in order and keeps the first that fits
+    fn rotate_piece(&mut self, new_rotation: Rotation) -> bool {
+        let kicks = self
+            .piece
+            .kick_table()
+            .kicks(&self.piece.current_rotation, &new_rotation);
+        for (test, (offset_x, offset_y)) in kicks.into_iter().enumerate() {
+            if self.does_piece_fit(
+                self.piece.current_piece,
+                &new_rotation,
+                self.piece.current_x + offset_x,
+                self.piece.current_y + offset_y,
+            ) {
+                self.piece.current_rotation = new_rotation;
+                self.piece.current_x += offset_x;
+                self.piece.current_y += offset_y;
+                self.last_kick = Some(Kick {
+                    test,
+                    offset_x,
+                    offset_y,
+                });
+                self.reset_lock_delay();
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn does_piece_fit(
+        &self,
+        p_tetrino: i16,
+        p_rotation: &Rotation,
+        p_pos_x: i16,
+        p_pos_y: i16,
+    ) -> bool {
+        let size = piece_size(p_tetrino);
+        for px in 0..size {
+            for py in 0..size {
+                let piece_index = Rotation::rotate(px, py, size, p_rotation);
+                if self.tetromino[p_tetrino as usize].as_bytes()[piece_index as usize] as char
+                    != 'X'
+                {
+                    continue;
+                }
+                let field_x = p_pos_x + px;
+                let field_y = p_pos_y + py;
+                if field_x < 0
+                    || field_y < 0
+                    || field_x >= self.field_width
+                    || field_y >= self.field_height
+                {
+                    return false;
+                }
+                let field_index = (field_y * self.field_width + field_x) as usize;
+                if *self.field.get_content_by_index(field_index) != ' ' {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+//Computed in u64 so no delay can overflow, the result always fits back into u32
+pub fn ms_to_frames(ms: u32) -> u32 {
+    ((ms as u64 * FRAMES_PER_SECOND as u64 + 500) / 1000) as u32
+}
+
+//Spawns centered, leaning left like the guideline when the box does not split evenly
+fn spawn_piece(p_piece: i16, field_width: i16, p_color: Color) -> TetrisShape {
+    let spawn_x = 1 + (field_width - 2 - piece_size(p_piece)) / 2;
+    let mut piece = TetrisShape::new(p_piece, spawn_x, 0);
+    piece.current_color = p_color;
+    piece
+}
+
+fn create_initial_field(field_width: i16, field_height: i16) -> Block {
+    let characters = [' ', '║', '═', '╚', '╝', '╗', '╔'];
+    let mut field = Block::new(field_width as u16, field_height as u16);
+    for x in 0..field_width {
+        for y in 0..field_height {
+            if x == 0 || x == field_width - 1 || y == field_height - 1 {
+                if x == 0 && y == field_height - 1 {
+                    field.change_content(
+                        x as u16,
+                        y as u16,
+                        characters[3],
+                        Color::White,
+                        Color::Black,
+                    );
+                } else if x == field_width - 1 && y == field_height - 1 {
+                    field.change_content(
+                        x as u16,
+                        y as u16,
+                        characters[4],
+                        Color::White,
+                        Color::Black,
+                    );
+                } else if y == field_height - 1 {
+                    field.change_content(
+                        x as u16,
+                        y as u16,
+                        characters[2],
+                        Color::White,
+                        Color::Black,
+                    );
+                } else if x == 0 || x == field_width - 1 {
+                    field.change_content(
+                        x as u16,
+                        y as u16,
+                        characters[1],
+                        Color::White,
+                        Color::Black,
+                    );
+                }
+            } else {
+                field.change_content(
+                    x as u16,
+                    y as u16,
+                    characters[0],
+                    Color::White,
+                    Color::Black,
+                );
+            }
+        }
+    }
+    field
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::shape::{build_tetromino, PIECE_I};
+
+    //10 columns and 20 rows inside the walls, lines clear on the frame they fill
+    fn new_game() -> Game {
+        let mut settings = GameSettings::new(12, 21);
+        settings.seed = 1;
+        settings.line_clear_delay_ms = 0;
+        Game::new(build_tetromino(), &settings)
+    }
+
+    fn fill_row(p_game: &mut Game, p_row: i16, p_gaps: &[i16]) {
+        for px in 1..p_game.field_width - 1 {
+            if !p_gaps.contains(&px) {
+                fill_cell(p_game, px, p_row);
+            }
+        }
+    }
+
+    fn fill_cell(p_game: &mut Game, p_pos_x: i16, p_pos_y: i16) {
+        p_game
+            .field
+            .change_content(p_pos_x as u16, p_pos_y as u16, '0', Color::Grey, Color::Red);
+    }
+
+    fn set_piece(p_game: &mut Game, p_piece: i16) {
+        let color = p_game.piece_color(p_piece);
+        p_game.piece = spawn_piece(p_piece, p_game.field_width, color);
+    }
+
+    fn hard_drop() -> Input {
+        Input {
+            hard_drop: true,
+            ..Input::new()
+        }
+    }
+
+    //Moves, turns and drops pieces on a fixed schedule so two runs get the same input.
+    //Each piece is tapped against the left wall and then a few columns back.
+    fn play(p_game: &mut Game, p_frames: u64) {
+        for _ in 0..p_frames {
+            let frame = p_game.frames_played() % 30;
+            let column = p_game.pieces_placed() as u64 % 8;
+            let input = Input {
+                left: frame < 10 && frame.is_multiple_of(2),
+                right: (10..10 + 2 * column).contains(&frame) && frame.is_multiple_of(2),
+                rotate: frame == 1 && p_game.pieces_placed().is_multiple_of(3),
+                hold: frame == 0 && p_game.pieces_placed() % 5 == 4,
+                hard_drop: frame == 29,
+                ..Input::new()
+            };
+            p_game.step(&input);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_game() {
+        for randomizer in RandomizerKind::ALL {
+            let mut settings = GameSettings::new(12, 21);
+            settings.seed = 42;
+            settings.randomizer = randomizer;
+            let mut first = Game::new(build_tetromino(), &settings);
+            let mut second = Game::new(build_tetromino(), &settings);
+            play(&mut first, 360);
+            play(&mut second, 360);
+            assert!(first.pieces_placed() > 10 && !first.is_game_over());
+            assert_eq!(first.snapshot(), second.snapshot());
+        }
+    }
+
+    #[test]
+    fn another_seed_deals_other_pieces() {
+        let mut settings = GameSettings::new(12, 21);
+        let mut pieces = Vec::new();
+        for seed in [1, 2] {
+            settings.seed = seed;
+            let game = Game::new(build_tetromino(), &settings);
+            let mut dealt = vec![game.piece().current_piece];
+            dealt.extend(game.next_pieces());
+            pieces.push(dealt);
+        }
+        assert_ne!(pieces[0], pieces[1]);
+    }
+
+    #[test]
+    fn a_restored_game_carries_on_like_the_original() {
+        let mut settings = GameSettings::new(12, 21);
+        settings.seed = 9;
+        for randomizer in RandomizerKind::ALL {
+            settings.randomizer = randomizer;
+            let mut game = Game::new(build_tetromino(), &settings);
+            play(&mut game, 170);
+            let mut restored = Game::restore(build_tetromino(), game.snapshot()).unwrap();
+            assert_eq!(restored.snapshot(), game.snapshot());
+            play(&mut game, 190);
+            play(&mut restored, 190);
+            assert_eq!(restored.snapshot(), game.snapshot());
+        }
+    }
+
+    #[test]
+    fn a_game_restored_while_lines_clear_finishes_the_clear() {
+        let mut settings = GameSettings::new(12, 21);
+        settings.seed = 1;
+        let mut game = Game::new(build_tetromino(), &settings);
+        fill_row(&mut game, 19, &[4, 5, 6, 7]);
+        set_piece(&mut game, PIECE_I);
+        game.step(&hard_drop());
+        game.step(&Input::new());
+        let mut restored = Game::restore(build_tetromino(), game.snapshot()).unwrap();
+        assert!(matches!(restored.state(), GameState::ClearingLines(_)));
+        play(&mut game, 60);
+        play(&mut restored, 60);
+        assert_eq!(game.lines_cleared(), 1);
+        assert_eq!(restored.snapshot(), game.snapshot());
+    }
+
+    #[test]
+    fn a_snapshot_the_game_cannot_use_is_refused() {
+        let game = new_game();
+        let mut snapshot = game.snapshot();
+        snapshot.queue.push(PIECE_COUNT);
+        assert!(Game::restore(build_tetromino(), snapshot).is_err());
+        let mut snapshot = game.snapshot();
+        snapshot.piece.current_y = 19;
+        assert!(Game::restore(build_tetromino(), snapshot).is_err());
+        let mut snapshot = game.snapshot();
+        snapshot.state = GameState::ClearingLines(3);
+        assert!(Game::restore(build_tetromino(), snapshot).is_err());
+        let mut snapshot = game.snapshot();
+        snapshot.randomizer_state = vec![0; 8];
+        assert!(Game::restore(build_tetromino(), snapshot).is_err());
+    }
+
+    #[test]
+    fn gravity_moves_the_piece_down_a_row() {
+        let mut game = new_game();
+        let start_y = game.piece().current_y;
+        //Level 1 falls a little less than a row a second
+        for _ in 0..FRAMES_PER_SECOND {
+            game.step(&Input::new());
+        }
+        assert_eq!(game.piece().current_y, start_y);
+        game.step(&Input::new());
+        assert_eq!(game.piece().current_y, start_y + 1);
+    }
+
+    #[test]
+    fn hard_drop_locks_the_piece_on_the_floor() {
+        let mut game = new_game();
+        set_piece(&mut game, PIECE_I);
+        let events = game.step(&hard_drop());
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, GameEvent::PieceLocked)));
+        assert_eq!(game.pieces_placed(), 1);
+        //The I piece lies flat in columns 4 to 7 of the bottom row
+        for px in 1..game.field_width - 1 {
+            assert_eq!(game.is_occupied(px, 19), (4..=7).contains(&px));
+        }
+        //Two points for every row it fell
+        assert_eq!(game.score(), 2 * 18);
+    }
+
+    #[test]
+    fn grounded_piece_locks_once_the_delay_runs_out() {
+        let mut game = new_game();
+        let delay = game.lock_delay_frames();
+        assert_eq!(delay, ms_to_frames(500));
+        let sonic_drop = Input {
+            sonic_drop: true,
+            ..Input::new()
+        };
+        game.step(&sonic_drop);
+        for _ in 1..delay - 1 {
+            game.step(&Input::new());
+        }
+        assert_eq!(game.pieces_placed(), 0);
+        game.step(&Input::new());
+        assert_eq!(game.pieces_placed(), 1);
+    }
+
+    #[test]
+    fn moving_a_grounded_piece_resets_the_delay_a_limited_number_of_times() {
+        let mut game = new_game();
+        game.sonic_drop();
+        let delay = game.lock_delay_frames();
+        //Taps left and right in turn so the piece moves every other frame
+        let taps = [
+            Input {
+                left: true,
+                ..Input::new()
+            },
+            Input::new(),
+            Input {
+                right: true,
+                ..Input::new()
+            },
+            Input::new(),
+        ];
+        let mut frames = 0;
+        while game.pieces_placed() == 0 {
+            game.step(&taps[frames as usize % taps.len()]);
+            frames += 1;
+        }
+        assert!(frames > delay + MAX_LOCK_RESETS);
+        assert!(frames <= delay + 2 * (MAX_LOCK_RESETS + 1));
+    }
+
+    #[test]
+    fn a_full_line_is_cleared_and_the_rows_above_fall() {
+        let mut game = new_game();
+        fill_row(&mut game, 19, &[4, 5, 6, 7]);
+        fill_cell(&mut game, 1, 18);
+        set_piece(&mut game, PIECE_I);
+        let events = game.step(&hard_drop());
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, GameEvent::LinesCleared(1))));
+        assert_eq!(game.lines_cleared(), 1);
+        assert!(game.is_occupied(1, 19));
+        for px in 2..game.field_width - 1 {
+            assert!(!game.is_occupied(px, 19));
+        }
+        assert!(!game.is_occupied(1, 18));
+    }
+
+    #[test]
+    fn lines_wait_for_the_clear_delay_before_the_next_piece() {
+        let mut settings = GameSettings::new(12, 21);
+        settings.seed = 1;
+        let mut game = Game::new(build_tetromino(), &settings);
+        let clear_frames = ms_to_frames(settings.line_clear_delay_ms);
+        fill_row(&mut game, 19, &[4, 5, 6, 7]);
+        set_piece(&mut game, PIECE_I);
+        game.step(&hard_drop());
+        assert_eq!(game.state(), GameState::ClearingLines(clear_frames));
+        assert!(!game.has_active_piece());
+        for _ in 1..clear_frames {
+            game.step(&Input::new());
+        }
+        assert!(game.is_occupied(1, 19));
+        game.step(&Input::new());
+        assert_eq!(game.state(), GameState::Falling);
+        assert!(!game.is_occupied(1, 19));
+    }
+
+    #[test]
+    fn a_t_rotated_into_a_slot_is_a_t_spin_double() {
+        let mut game = new_game();
+        //A slot three wide with one cell below it and a block hanging over its left side
+        fill_cell(&mut game, 4, 17);
+        fill_row(&mut game, 18, &[4, 5, 6]);
+        fill_row(&mut game, 19, &[5]);
+        set_piece(&mut game, PIECE_T);
+        game.piece.current_rotation = Rotation::R90;
+        game.piece.current_x = 4;
+        game.piece.current_y = 17;
+        let rotate = Input {
+            rotate: true,
+            ..Input::new()
+        };
+        game.step(&rotate);
+        assert_eq!(game.piece().current_rotation, Rotation::R180);
+        let events = game.step(&hard_drop());
+        let score_event = events.iter().find_map(|event| match event {
+            GameEvent::Scored(score_event) => Some(score_event),
+            _ => None,
+        });
+        let score_event = score_event.expect("the spin scores");
+        assert_eq!(score_event.spin, SpinKind::Full);
+        assert_eq!(score_event.lines, 2);
+        assert_eq!(score_event.points, 1200);
+    }
+
+    #[test]
+    fn a_t_that_did_not_rotate_into_the_slot_is_no_spin() {
+        let mut game = new_game();
+        fill_cell(&mut game, 4, 17);
+        fill_row(&mut game, 18, &[4, 5, 6]);
+        fill_row(&mut game, 19, &[5]);
+        set_piece(&mut game, PIECE_T);
+        game.piece.current_rotation = Rotation::R180;
+        game.piece.current_x = 4;
+        game.piece.current_y = 17;
+        let events = game.step(&hard_drop());
+        let spins = events.iter().filter_map(|event| match event {
+            GameEvent::Scored(score_event) => Some(score_event.spin),
+            _ => None,
+        });
+        assert_eq!(spins.collect::<Vec<_>>(), [SpinKind::None]);
+        assert_eq!(game.lines_cleared(), 2);
+    }
+
+    #[test]
+    fn a_piece_that_cannot_spawn_ends_the_game() {
+        let mut game = new_game();
+        //The I piece locks in the spawn rows and every piece overlaps it
+        fill_row(&mut game, 2, &[1]);
+        set_piece(&mut game, PIECE_I);
+        let events = game.step(&hard_drop());
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, GameEvent::GameOver)));
+        assert!(game.is_game_over());
+        assert!(game.step(&Input::new()).is_empty());
+    }
+}
@@ -0,0 +1,23 @@
+//Fixed point fraction of a row, 65536 is one row per frame (1G)
+pub const ROW: u64 = 65536;
+pub const LINES_PER_LEVEL: u32 = 10;
+
+//Guideline curve, (0.8 - (level - 1) * 0.007) ^ (level - 1) seconds per row, turned into
+//G for levels 1 to 20. It passes 20G at level 19 where everything lands at once.
+const GRAVITY_TABLE: [u64; 20] = [
+    1092, 1377, 1768, 2311, 3075, 4169, 5759, 8107, 11634, 17026, 25416, 38709, 60169, 95483,
+    154742, 256187, 433425, 749597, 1310720, 1310720,
+];
+
+//Rows of fall for every engine frame, in units of ROW
+pub fn gravity_per_frame(level: u32) -> u64 {
+    let index = (level.max(1) as usize - 1).min(GRAVITY_TABLE.len() - 1);
+    GRAVITY_TABLE[index]
+}
+
+//The lock delay stays whole through the normal levels and loses 5% per level
+//once gravity is maxed out, down to half of the configured value
+pub fn lock_delay_percent(level: u32) -> u32 {
+    let past_max = level.saturating_sub(GRAVITY_TABLE.len() as u32);
+    100 - past_max.saturating_mul(5).min(50)
+}
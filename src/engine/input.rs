@@ -0,0 +1,62 @@
+//left, right and down are held states, the rest are only set on the frame the key went down
+#[derive(Default)]
+pub struct Input {
+    pub right: bool,
+    pub left: bool,
+    pub down: bool,
+    pub rotate: bool,
+    pub rotate_counter_clockwise: bool,
+    pub rotate_half_turn: bool,
+    pub hold: bool,
+    pub hard_drop: bool,
+    pub sonic_drop: bool,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input {
+            right: false,
+            left: false,
+            down: false,
+            rotate: false,
+            rotate_counter_clockwise: false,
+            rotate_half_turn: false,
+            hold: false,
+            hard_drop: false,
+            sonic_drop: false,
+        }
+    }
+
+    //One bit per field in declaration order, replays store the input of a frame like this
+    pub fn to_bits(&self) -> u16 {
+        [
+            self.right,
+            self.left,
+            self.down,
+            self.rotate,
+            self.rotate_counter_clockwise,
+            self.rotate_half_turn,
+            self.hold,
+            self.hard_drop,
+            self.sonic_drop,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (index, set)| bits | (*set as u16) << index)
+    }
+
+    pub fn from_bits(bits: u16) -> Input {
+        let bit = |index: u16| bits & (1 << index) != 0;
+        Input {
+            right: bit(0),
+            left: bit(1),
+            down: bit(2),
+            rotate: bit(3),
+            rotate_counter_clockwise: bit(4),
+            rotate_half_turn: bit(5),
+            hold: bit(6),
+            hard_drop: bit(7),
+            sonic_drop: bit(8),
+        }
+    }
+}
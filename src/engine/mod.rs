@@ -0,0 +1,30 @@
+pub mod auto_shift;
+pub mod game;
+pub mod gravity;
+pub mod input;
+pub mod mode;
+pub mod randomizer;
+pub mod scoring;
+pub mod settings;
+pub mod shape;
+pub mod snapshot;
+pub use self::auto_shift::AutoShift;
+pub use self::game::ms_to_frames;
+pub use self::game::Game;
+pub use self::game::GameEvent;
+pub use self::game::GameState;
+pub use self::game::FRAMES_PER_SECOND;
+pub use self::game::MAX_LOCK_RESETS;
+pub use self::game::MAX_PREVIEW;
+pub use self::input::Input;
+pub use self::mode::GameMode;
+pub use self::randomizer::Randomizer;
+pub use self::randomizer::RandomizerKind;
+pub use self::scoring::ScoreEvent;
+pub use self::scoring::Scoring;
+pub use self::scoring::SpinKind;
+pub use self::settings::GameSettings;
+pub use self::shape::build_tetromino;
+pub use self::shape::piece_color;
+pub use self::shape::TetrisShape;
+pub use self::snapshot::GameSnapshot;
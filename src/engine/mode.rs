@@ -0,0 +1,56 @@
+use super::game::FRAMES_PER_SECOND;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameMode {
+    Marathon,
+    Sprint,
+    Ultra,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [GameMode::Marathon, GameMode::Sprint, GameMode::Ultra];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Marathon => "marathon",
+            GameMode::Sprint => "sprint",
+            GameMode::Ultra => "ultra",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        GameMode::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            GameMode::Marathon => "Endless, faster every 10 lines",
+            GameMode::Sprint => "Clear 40 lines quickly",
+            GameMode::Ultra => "Score big in 2 minutes",
+        }
+    }
+
+    //The game is won once this many lines are cleared
+    pub fn line_goal(&self) -> Option<u32> {
+        match self {
+            GameMode::Sprint => Some(40),
+            _ => None,
+        }
+    }
+
+    //The game ends after this many frames
+    pub fn time_limit(&self) -> Option<u64> {
+        match self {
+            GameMode::Ultra => Some(120 * FRAMES_PER_SECOND as u64),
+            _ => None,
+        }
+    }
+
+    //Only marathon speeds up, the timed modes stay on the start level
+    pub fn levels_up(&self) -> bool {
+        *self == GameMode::Marathon
+    }
+}
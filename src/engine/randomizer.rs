@@ -0,0 +1,284 @@
+use super::shape::PIECE_COUNT;
+use super::shape::PIECE_O;
+use super::shape::PIECE_S;
+use super::shape::PIECE_Z;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand::RngCore;
+
+pub trait Randomizer {
+    fn next_piece(&mut self, rng: &mut dyn RngCore) -> i16;
+    //The pieces it remembers, enough to carry on after a saved game is loaded
+    fn state(&self) -> Vec<i16>;
+    fn restore(&mut self, state: &[i16]) -> Result<(), String>;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RandomizerKind {
+    Random,
+    Bag7,
+    Bag14,
+    Tgm,
+}
+
+impl RandomizerKind {
+    pub const ALL: [RandomizerKind; 4] = [
+        RandomizerKind::Random,
+        RandomizerKind::Bag7,
+        RandomizerKind::Bag14,
+        RandomizerKind::Tgm,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RandomizerKind::Random => "random",
+            RandomizerKind::Bag7 => "7-bag",
+            RandomizerKind::Bag14 => "14-bag",
+            RandomizerKind::Tgm => "tgm",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RandomizerKind> {
+        RandomizerKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn create(&self) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::Random => Box::new(UniformRandomizer),
+            RandomizerKind::Bag7 => Box::new(BagRandomizer::new(1)),
+            RandomizerKind::Bag14 => Box::new(BagRandomizer::new(2)),
+            RandomizerKind::Tgm => Box::new(HistoryRandomizer::new()),
+        }
+    }
+}
+
+//Every piece is equally likely every time, droughts and floods included
+pub struct UniformRandomizer;
+
+impl Randomizer for UniformRandomizer {
+    fn next_piece(&mut self, rng: &mut dyn RngCore) -> i16 {
+        rng.gen_range(0..PIECE_COUNT)
+    }
+
+    fn state(&self) -> Vec<i16> {
+        Vec::new()
+    }
+
+    fn restore(&mut self, state: &[i16]) -> Result<(), String> {
+        if !state.is_empty() {
+            return Err(String::from("the random randomizer keeps no pieces"));
+        }
+        Ok(())
+    }
+}
+
+//Deals out shuffled bags holding every piece `copies` times
+pub struct BagRandomizer {
+    copies: i16,
+    bag: Vec<i16>,
+}
+
+impl BagRandomizer {
+    pub fn new(copies: i16) -> BagRandomizer {
+        BagRandomizer {
+            copies,
+            bag: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for BagRandomizer {
+    fn next_piece(&mut self, rng: &mut dyn RngCore) -> i16 {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend(0..PIECE_COUNT);
+            }
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().unwrap_or(0)
+    }
+
+    //What is left of the bag, the last piece is dealt first
+    fn state(&self) -> Vec<i16> {
+        self.bag.clone()
+    }
+
+    //What is left of a bag can hold each piece at most as often as a full one
+    fn restore(&mut self, state: &[i16]) -> Result<(), String> {
+        let too_many = (0..PIECE_COUNT).any(|piece| {
+            state.iter().filter(|left| **left == piece).count() > self.copies as usize
+        });
+        if !valid_pieces(state) || too_many {
+            return Err(String::from("the bag does not fit the randomizer"));
+        }
+        self.bag = state.to_vec();
+        Ok(())
+    }
+}
+
+//TGM style, rerolls a few times when the piece is one of the last four dealt
+pub struct HistoryRandomizer {
+    history: [i16; 4],
+    first_piece: bool,
+}
+
+impl HistoryRandomizer {
+    const ROLLS: u32 = 6;
+
+    pub fn new() -> HistoryRandomizer {
+        HistoryRandomizer {
+            history: [PIECE_Z, PIECE_S, PIECE_S, PIECE_Z],
+            first_piece: true,
+        }
+    }
+}
+
+impl Default for HistoryRandomizer {
+    fn default() -> HistoryRandomizer {
+        HistoryRandomizer::new()
+    }
+}
+
+impl Randomizer for HistoryRandomizer {
+    fn next_piece(&mut self, rng: &mut dyn RngCore) -> i16 {
+        let mut piece = rng.gen_range(0..PIECE_COUNT);
+        if self.first_piece {
+            //The first piece is never one that forces an overhang
+            while piece == PIECE_Z || piece == PIECE_S || piece == PIECE_O {
+                piece = rng.gen_range(0..PIECE_COUNT);
+            }
+            self.first_piece = false;
+        } else {
+            for _ in 1..HistoryRandomizer::ROLLS {
+                if !self.history.contains(&piece) {
+                    break;
+                }
+                piece = rng.gen_range(0..PIECE_COUNT);
+            }
+        }
+        self.history.rotate_left(1);
+        self.history[3] = piece;
+        piece
+    }
+
+    //An empty history means no piece was dealt yet
+    fn state(&self) -> Vec<i16> {
+        if self.first_piece {
+            Vec::new()
+        } else {
+            self.history.to_vec()
+        }
+    }
+
+    fn restore(&mut self, state: &[i16]) -> Result<(), String> {
+        if state.is_empty() {
+            *self = HistoryRandomizer::new();
+            return Ok(());
+        }
+        if !valid_pieces(state) || state.len() != self.history.len() {
+            return Err(String::from("the history does not fit the randomizer"));
+        }
+        self.history.copy_from_slice(state);
+        self.first_piece = false;
+        Ok(())
+    }
+}
+
+fn valid_pieces(p_pieces: &[i16]) -> bool {
+    p_pieces
+        .iter()
+        .all(|piece| (0..PIECE_COUNT).contains(piece))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn deal(p_randomizer: &mut dyn Randomizer, p_rng: &mut ChaCha8Rng, p_count: usize) -> Vec<i16> {
+        (0..p_count)
+            .map(|_| p_randomizer.next_piece(p_rng))
+            .collect()
+    }
+
+    fn count_each(p_pieces: &[i16]) -> Vec<usize> {
+        (0..PIECE_COUNT)
+            .map(|piece| p_pieces.iter().filter(|dealt| **dealt == piece).count())
+            .collect()
+    }
+
+    #[test]
+    fn every_bag_holds_each_piece_the_same_number_of_times() {
+        for copies in [1, 2] {
+            let mut rng = ChaCha8Rng::seed_from_u64(7);
+            let mut randomizer = BagRandomizer::new(copies);
+            let bag_size = (copies * PIECE_COUNT) as usize;
+            for _ in 0..20 {
+                let bag = deal(&mut randomizer, &mut rng, bag_size);
+                assert_eq!(
+                    count_each(&bag),
+                    vec![copies as usize; PIECE_COUNT as usize]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_restored_bag_deals_what_was_left() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut randomizer = BagRandomizer::new(1);
+        deal(&mut randomizer, &mut rng, 3);
+        let mut restored = BagRandomizer::new(1);
+        restored.restore(&randomizer.state()).unwrap();
+        let mut restored_rng = rng.clone();
+        assert_eq!(
+            deal(&mut restored, &mut restored_rng, 11),
+            deal(&mut randomizer, &mut rng, 11)
+        );
+        assert!(restored.restore(&[0; 8]).is_err());
+        assert!(restored.restore(&[PIECE_COUNT]).is_err());
+    }
+
+    #[test]
+    fn a_bag_holding_a_piece_too_often_is_refused() {
+        let mut seven = BagRandomizer::new(1);
+        assert!(seven.restore(&[0, 0]).is_err());
+        assert!(seven.restore(&[3, 1, 3]).is_err());
+        assert!(seven.restore(&[6, 0, 1, 2, 3, 4, 5]).is_ok());
+        assert!(seven.restore(&[]).is_ok());
+        let mut fourteen = BagRandomizer::new(2);
+        assert!(fourteen.restore(&[0, 0, 4, 4]).is_ok());
+        assert!(fourteen.restore(&[0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn the_history_randomizer_never_starts_with_an_overhang() {
+        for seed in 0..200 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let first = HistoryRandomizer::new().next_piece(&mut rng);
+            assert!(![PIECE_S, PIECE_Z, PIECE_O].contains(&first));
+        }
+    }
+
+    #[test]
+    fn the_history_randomizer_rarely_repeats_a_recent_piece() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut randomizer = HistoryRandomizer::new();
+        let pieces = deal(&mut randomizer, &mut rng, 7000);
+        let repeats = pieces.windows(2).filter(|pair| pair[0] == pair[1]).count();
+        //Pure random would repeat one piece in seven, six rolls bring it far lower
+        assert!(repeats < pieces.len() / 50, "{} repeats", repeats);
+    }
+
+    #[test]
+    fn the_random_randomizer_deals_every_piece() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let pieces = deal(&mut UniformRandomizer, &mut rng, 700);
+        assert!(count_each(&pieces).iter().all(|count| *count > 0));
+        assert!(UniformRandomizer.restore(&[0]).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SpinKind {
+    None,
+    Mini,
+    Full,
+}
+
+//Everything that earned points when a piece locked, for the UI to announce
+#[derive(Clone, PartialEq, Debug)]
+pub struct ScoreEvent {
+    pub lines: u16,
+    pub spin: SpinKind,
+    pub back_to_back: bool,
+    pub combo: u32,
+    pub perfect_clear: bool,
+    pub points: u32,
+}
+
+impl ScoreEvent {
+    //Short lines of text, none of them wider than 11 characters
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+        match self.spin {
+            SpinKind::Full => labels.push(String::from("T-SPIN")),
+            SpinKind::Mini => labels.push(String::from("MINI T-SPIN")),
+            SpinKind::None => (),
+        }
+        match self.lines {
+            1 => labels.push(String::from("SINGLE")),
+            2 => labels.push(String::from("DOUBLE")),
+            3 => labels.push(String::from("TRIPLE")),
+            4 => labels.push(String::from("TETRIS")),
+            _ => (),
+        }
+        if self.back_to_back {
+            labels.push(String::from("BACK2BACK"));
+        }
+        if self.combo > 0 {
+            labels.push(format!("COMBO {}", self.combo));
+        }
+        if self.perfect_clear {
+            labels.push(String::from("ALL CLEAR"));
+        }
+        labels
+    }
+}
+
+//Guideline scoring, every value is multiplied by the level
+#[derive(Clone, PartialEq, Debug)]
+pub struct Scoring {
+    pub back_to_back: bool,
+    pub combo: Option<u32>,
+}
+
+impl Scoring {
+    pub fn new() -> Scoring {
+        Scoring {
+            back_to_back: false,
+            combo: None,
+        }
+    }
+
+    //Called for every locked piece, returns None when there is nothing to announce
+    pub fn lock(
+        &mut self,
+        lines: u16,
+        spin: SpinKind,
+        perfect_clear: bool,
+        level: u32,
+    ) -> Option<ScoreEvent> {
+        if lines == 0 {
+            self.combo = None;
+            if spin == SpinKind::None {
+                return None;
+            }
+        } else {
+            self.combo = Some(self.combo.map_or(0, |combo| combo + 1));
+        }
+        let base = match (spin, lines) {
+            (SpinKind::None, 1) => 100,
+            (SpinKind::None, 2) => 300,
+            (SpinKind::None, 3) => 500,
+            (SpinKind::None, 4) => 800,
+            (SpinKind::Mini, 0) => 100,
+            (SpinKind::Mini, 1) => 200,
+            (SpinKind::Mini, _) => 400,
+            (SpinKind::Full, 0) => 400,
+            (SpinKind::Full, 1) => 800,
+            (SpinKind::Full, 2) => 1200,
+            (SpinKind::Full, _) => 1600,
+            _ => 0,
+        };
+        //Tetrises and spins that clear lines are difficult, a run of them earns 50% more.
+        //Spins without lines keep the run going, any other clear ends it.
+        let difficult = lines == 4 || (spin != SpinKind::None && lines > 0);
+        let back_to_back = difficult && self.back_to_back;
+        if lines > 0 {
+            self.back_to_back = difficult;
+        }
+        let mut points = if back_to_back { base * 3 / 2 } else { base };
+        let combo = self.combo.unwrap_or(0);
+        points += 50 * combo;
+        if perfect_clear {
+            points += match lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if back_to_back => 3200,
+                _ => 2000,
+            };
+        }
+        Some(ScoreEvent {
+            lines,
+            spin,
+            back_to_back,
+            combo,
+            perfect_clear,
+            points: points * level,
+        })
+    }
+}
+
+impl Default for Scoring {
+    fn default() -> Scoring {
+        Scoring::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(p_event: Option<ScoreEvent>) -> u32 {
+        p_event.map_or(0, |event| event.points)
+    }
+
+    #[test]
+    fn line_clears_and_t_spins_score_by_the_guideline() {
+        let cases = [
+            (1, SpinKind::None, 100),
+            (2, SpinKind::None, 300),
+            (3, SpinKind::None, 500),
+            (4, SpinKind::None, 800),
+            (0, SpinKind::Mini, 100),
+            (1, SpinKind::Mini, 200),
+            (2, SpinKind::Mini, 400),
+            (0, SpinKind::Full, 400),
+            (1, SpinKind::Full, 800),
+            (2, SpinKind::Full, 1200),
+            (3, SpinKind::Full, 1600),
+        ];
+        for (lines, spin, expected) in cases {
+            let event = Scoring::new().lock(lines, spin, false, 3);
+            assert_eq!(points(event), expected * 3, "{} lines {:?}", lines, spin);
+        }
+    }
+
+    #[test]
+    fn a_lock_without_lines_or_spin_scores_nothing() {
+        assert_eq!(Scoring::new().lock(0, SpinKind::None, false, 1), None);
+    }
+
+    #[test]
+    fn back_to_back_needs_two_difficult_clears_in_a_row() {
+        let mut scoring = Scoring::new();
+        let first = scoring.lock(4, SpinKind::None, false, 1).unwrap();
+        assert!(!first.back_to_back);
+        scoring.lock(0, SpinKind::None, false, 1);
+        let second = scoring.lock(2, SpinKind::Full, false, 1).unwrap();
+        assert!(second.back_to_back);
+        assert_eq!(second.points, 1200 * 3 / 2);
+        //A single ends the run, the tetris after it is back to normal
+        scoring.lock(1, SpinKind::None, false, 1);
+        let third = scoring.lock(4, SpinKind::None, false, 1).unwrap();
+        assert!(!third.back_to_back);
+    }
+
+    #[test]
+    fn a_spin_without_lines_keeps_back_to_back_going() {
+        let mut scoring = Scoring::new();
+        scoring.lock(4, SpinKind::None, false, 1);
+        let spin = scoring.lock(0, SpinKind::Full, false, 1).unwrap();
+        assert!(!spin.back_to_back);
+        assert!(scoring.back_to_back);
+        assert!(
+            scoring
+                .lock(4, SpinKind::None, false, 1)
+                .unwrap()
+                .back_to_back
+        );
+    }
+
+    #[test]
+    fn combos_grow_with_every_clear_and_end_on_a_lock_without_one() {
+        let mut scoring = Scoring::new();
+        let combos: Vec<u32> = (0..3)
+            .map(|_| scoring.lock(1, SpinKind::None, false, 1).unwrap().combo)
+            .collect();
+        assert_eq!(combos, [0, 1, 2]);
+        assert_eq!(
+            points(scoring.lock(1, SpinKind::None, false, 2)),
+            (100 + 50 * 3) * 2
+        );
+        scoring.lock(0, SpinKind::None, false, 1);
+        assert_eq!(scoring.lock(1, SpinKind::None, false, 1).unwrap().combo, 0);
+    }
+
+    #[test]
+    fn perfect_clears_add_their_bonus() {
+        let event = Scoring::new().lock(4, SpinKind::None, true, 1);
+        assert_eq!(points(event), 800 + 2000);
+        let mut scoring = Scoring::new();
+        scoring.lock(4, SpinKind::None, false, 1);
+        scoring.lock(0, SpinKind::None, false, 1);
+        let event = scoring.lock(4, SpinKind::None, true, 1);
+        assert_eq!(points(event), 1200 + 3200);
+    }
+}
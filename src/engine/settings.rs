@@ -0,0 +1,42 @@
+use super::mode::GameMode;
+use super::randomizer::RandomizerKind;
+use super::shape::piece_color;
+use super::shape::PIECE_COUNT;
+use crossterm::style::Color;
+
+#[derive(Clone)]
+pub struct GameSettings {
+    pub field_width: i16,
+    pub field_height: i16,
+    pub mode: GameMode,
+    pub preview_count: usize,
+    pub randomizer: RandomizerKind,
+    pub seed: u64,
+    pub start_level: u32,
+    pub lock_delay_ms: u32,
+    pub line_clear_delay_ms: u32,
+    pub das_ms: u32,
+    pub arr_ms: u32,
+    pub soft_drop_ms: u32,
+    pub piece_colors: [Color; PIECE_COUNT as usize],
+}
+
+impl GameSettings {
+    pub fn new(field_width: i16, field_height: i16) -> GameSettings {
+        GameSettings {
+            field_width,
+            field_height,
+            mode: GameMode::Marathon,
+            preview_count: 3,
+            randomizer: RandomizerKind::Bag7,
+            seed: rand::random(),
+            start_level: 1,
+            lock_delay_ms: 500,
+            line_clear_delay_ms: 400,
+            das_ms: 167,
+            arr_ms: 33,
+            soft_drop_ms: 50,
+            piece_colors: [0, 1, 2, 3, 4, 5, 6].map(piece_color),
+        }
+    }
+}
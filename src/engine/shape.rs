@@ -0,0 +1,115 @@
+use crate::rotation::KickTable;
+use crate::rotation::Rotation;
+use crossterm::style::Color;
+
+//Piece indices as laid out by build_tetromino
+pub const PIECE_COUNT: i16 = 7;
+pub const PIECE_I: i16 = 0;
+pub const PIECE_Z: i16 = 1;
+pub const PIECE_S: i16 = 2;
+pub const PIECE_O: i16 = 5;
+pub const PIECE_T: i16 = 6;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TetrisShape {
+    pub current_piece: i16,
+    pub current_rotation: Rotation,
+    pub current_color: Color,
+    pub current_x: i16,
+    pub current_y: i16,
+}
+
+impl TetrisShape {
+    pub fn new(current_piece: i16, current_x: i16, current_y: i16) -> TetrisShape {
+        TetrisShape {
+            current_piece,
+            current_color: piece_color(current_piece),
+            current_rotation: Rotation::R0,
+            current_x,
+            current_y,
+        }
+    }
+
+    pub fn size(&self) -> i16 {
+        piece_size(self.current_piece)
+    }
+
+    pub fn kick_table(&self) -> KickTable {
+        match self.current_piece {
+            PIECE_I => KickTable::I,
+            PIECE_O => KickTable::O,
+            _ => KickTable::Jlstz,
+        }
+    }
+}
+
+pub fn piece_color(p_piece: i16) -> Color {
+    let piece_colors = [
+        Color::Cyan,
+        Color::Green,
+        Color::Blue,
+        Color::Yellow,
+        Color::Magenta,
+        Color::Red,
+        Color::Green,
+    ];
+    piece_colors[p_piece as usize]
+}
+
+//Width of the square box a piece rotates in, the I piece needs 4 and the O piece 2
+pub fn piece_size(p_piece: i16) -> i16 {
+    match p_piece {
+        PIECE_I => 4,
+        PIECE_O => 2,
+        _ => 3,
+    }
+}
+
+pub fn build_tetromino() -> Vec<String> {
+    //This could be more optimal left for readability
+    //Shapes are in their SRS spawn orientation, see piece_size for the box they rotate in
+    let mut tetromino = vec![];
+    let mut shape = String::from("");
+    shape.push_str("....");
+    shape.push_str("XXXX");
+    shape.push_str("....");
+    shape.push_str("....");
+    tetromino.push(shape);
+    let mut shape = String::from("");
+    shape.push_str("XX..");
+    shape.push_str(".XX.");
+    shape.push_str("....");
+    shape.push_str("....");
+    tetromino.push(shape);
+    let mut shape = String::from("");
+    shape.push_str(".XX.");
+    shape.push_str("XX..");
+    shape.push_str("....");
+    shape.push_str("....");
+    tetromino.push(shape);
+    let mut shape = String::from("");
+    shape.push_str("..X.");
+    shape.push_str("XXX.");
+    shape.push_str("....");
+    shape.push_str("....");
+    tetromino.push(shape);
+    let mut shape = String::from("");
+    shape.push_str("X...");
+    shape.push_str("XXX.");
+    shape.push_str("....");
+    shape.push_str("....");
+    tetromino.push(shape);
+    let mut shape = String::from("");
+    shape.push_str("XX..");
+    shape.push_str("XX..");
+    shape.push_str("....");
+    shape.push_str("....");
+    tetromino.push(shape);
+    let mut shape = String::from("");
+    shape.push_str(".X..");
+    shape.push_str("XXX.");
+    shape.push_str("....");
+    shape.push_str("....");
+    tetromino.push(shape);
+    tetromino
+}
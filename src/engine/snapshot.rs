@@ -0,0 +1,51 @@
+use super::auto_shift::AutoShift;
+use super::game::GameState;
+use super::mode::GameMode;
+use super::randomizer::RandomizerKind;
+use super::scoring::Scoring;
+use super::shape::TetrisShape;
+use super::shape::PIECE_COUNT;
+use crate::render::Block;
+use crate::rotation::Kick;
+use crossterm::style::Color;
+
+//Everything a game needs to carry on where it stopped. The generator is kept as its
+//seed and how many words it handed out, the randomizer as the pieces it remembers.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameSnapshot {
+    pub field: Block,
+    pub field_width: i16,
+    pub field_height: i16,
+    pub mode: GameMode,
+    pub piece: TetrisShape,
+    pub piece_colors: [Color; PIECE_COUNT as usize],
+    pub queue: Vec<i16>,
+    pub randomizer: RandomizerKind,
+    pub randomizer_state: Vec<i16>,
+    pub seed: u64,
+    pub rng_word_pos: u128,
+    pub lines: Vec<i16>,
+    pub state: GameState,
+    pub line_clear_frames: u32,
+    pub score: u32,
+    pub scoring: Scoring,
+    pub start_level: u32,
+    pub level: u32,
+    pub lines_cleared: u32,
+    pub gravity_progress: u64,
+    pub pieces_placed: u32,
+    pub frames_played: u64,
+    pub last_kick: Option<Kick>,
+    pub lock_delay_ms: u32,
+    pub auto_shift: AutoShift,
+    pub soft_drop_frames: u32,
+    pub soft_drop_timer: u32,
+    pub soft_drop_held: bool,
+    pub lock_timer: u32,
+    pub lock_resets: u32,
+    pub lowest_y: i16,
+    pub held_piece: Option<i16>,
+    pub can_hold: bool,
+    pub goal_reached: bool,
+    pub game_over: bool,
+}
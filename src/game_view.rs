@@ -0,0 +1,230 @@
+use crossterm::style::Color;
+use tetris::engine::Game;
+use tetris::engine::GameMode;
+use tetris::engine::TetrisShape;
+use tetris::engine::FRAMES_PER_SECOND;
+use tetris::render::screen::ScreenElement;
+use tetris::render::Frame;
+use tetris::render::FrameStyle;
+use tetris::render::Screen;
+use tetris::render::Text;
+use tetris::rotation::Rotation;
+
+//The most lines a score announcement takes, see ScoreEvent::labels
+const MAX_LABELS: u16 = 5;
+
+//The field and the panels around it, overlays are drawn on top afterwards
+pub struct GameView {
+    //The panels start right of the field
+    panel_x: u16,
+    score: Frame,
+    score_title: Text,
+    hold: Frame,
+    hold_title: Text,
+    level: Frame,
+    level_title: Text,
+    lines: Frame,
+    lines_title: Text,
+    time: Frame,
+    time_title: Text,
+    next: Frame,
+    next_title: Text,
+}
+
+impl GameView {
+    pub fn new(game: &Game) -> GameView {
+        let next_height = 3 * game.next_pieces().len() as u16 + 1;
+        GameView {
+            panel_x: 3 + game.field_width() as u16,
+            score: Frame::new(19, 3, FrameStyle::DoubleLine, Color::White, Color::Black),
+            score_title: Text::new(String::from(" SCORE "), Color::Cyan, Color::Black),
+            hold: Frame::new(10, 4, FrameStyle::DoubleLine, Color::White, Color::Black),
+            hold_title: Text::new(String::from(" HOLD "), Color::Cyan, Color::Black),
+            level: Frame::new(10, 3, FrameStyle::DoubleLine, Color::White, Color::Black),
+            level_title: Text::new(String::from(" LEVEL "), Color::Cyan, Color::Black),
+            lines: Frame::new(10, 3, FrameStyle::DoubleLine, Color::White, Color::Black),
+            lines_title: Text::new(String::from(" LINES "), Color::Cyan, Color::Black),
+            time: Frame::new(10, 3, FrameStyle::DoubleLine, Color::White, Color::Black),
+            time_title: Text::new(String::from(" TIME "), Color::Cyan, Color::Black),
+            next: Frame::new(
+                8,
+                next_height,
+                FrameStyle::DoubleLine,
+                Color::White,
+                Color::Black,
+            ),
+            next_title: Text::new(String::from(" NEXT "), Color::Cyan, Color::Black),
+        }
+    }
+
+    //Only the frames and numbers, nothing that would help to plan ahead
+    pub fn render_hidden(&self, screen: &mut Screen, game: &Game) {
+        let field = game.field();
+        for x in 0..field.get_width() {
+            for y in 0..field.get_height() {
+                let border = x == 0 || x + 1 == field.get_width() || y + 1 == field.get_height();
+                if border {
+                    let part = field.get_part(x, y);
+                    let foreground_color = field.get_foreground_color(x, y);
+                    let background_color = field.get_background_color(x, y);
+                    screen.add_directly(part, foreground_color, background_color, x + 2, y + 2);
+                }
+            }
+        }
+        self.render_panels(screen, game, false);
+    }
+
+    pub fn render(
+        &self,
+        screen: &mut Screen,
+        game: &Game,
+        show_ghost: bool,
+        announcement: &[String],
+    ) {
+        screen.add_element_at(game.field(), 2, 2);
+        self.render_panels(screen, game, true);
+        let ghost_distance = if show_ghost {
+            Some(game.drop_distance())
+        } else {
+            None
+        };
+        if game.has_active_piece() && !game.is_game_over() {
+            render_current_piece(game.tetromino(), screen, game.piece(), ghost_distance);
+        }
+        for (index, label) in announcement.iter().enumerate() {
+            let pos_y = announcement_y(game.mode()) + index as u16;
+            let pos_x = self.panel_x;
+            screen.add_string_at(label.clone(), Color::Yellow, Color::Black, pos_x, pos_y);
+        }
+    }
+
+    fn render_panels(&self, screen: &mut Screen, game: &Game, show_pieces: bool) {
+        let x = self.panel_x;
+        screen.add_element_at(&self.score, x, 2);
+        screen.add_element_at(&self.score_title, x + 6, 2);
+        let score = format!("{:0>11}", game.score());
+        screen.add_string_at(score, Color::DarkBlue, Color::Black, x + 4, 3);
+        screen.add_element_at(&self.hold, x, 5);
+        screen.add_element_at(&self.hold_title, x + 2, 5);
+        if let Some(held_piece) = game.held_piece().filter(|_| show_pieces) {
+            let color = if game.can_hold() {
+                game.piece_color(held_piece)
+            } else {
+                Color::DarkGrey
+            };
+            render_piece_preview(game.tetromino(), screen, held_piece, color, x + 3, 6);
+        }
+        screen.add_element_at(&self.level, x, 9);
+        screen.add_element_at(&self.level_title, x + 1, 9);
+        let level = format!("{:>6}", game.level());
+        screen.add_string_at(level, Color::DarkBlue, Color::Black, x + 2, 10);
+        screen.add_element_at(&self.lines, x, 12);
+        screen.add_element_at(&self.lines_title, x + 1, 12);
+        let lines = match game.mode().line_goal() {
+            Some(goal) => format!("{:>6}", format!("{}/{}", game.lines_cleared(), goal)),
+            None => format!("{:>6}", game.lines_cleared()),
+        };
+        screen.add_string_at(lines, Color::DarkBlue, Color::Black, x + 2, 13);
+        if shows_time(game.mode()) {
+            screen.add_element_at(&self.time, x, 15);
+            screen.add_element_at(&self.time_title, x + 2, 15);
+            let frames = game.time_left().unwrap_or(game.frames_played());
+            let time = format!("{:>6}", format_time(frames));
+            screen.add_string_at(time, Color::DarkBlue, Color::Black, x + 2, 16);
+        }
+        screen.add_element_at(&self.next, x + 11, 5);
+        screen.add_element_at(&self.next_title, x + 12, 5);
+        if !show_pieces {
+            return;
+        }
+        for (index, next_piece) in game.next_pieces().iter().enumerate() {
+            let pos_y = 6 + 3 * index as u16;
+            let color = game.piece_color(*next_piece);
+            render_piece_preview(game.tetromino(), screen, *next_piece, color, x + 13, pos_y);
+        }
+    }
+}
+
+//Rows the panels and an announcement below them need, the layout is at least this high
+pub fn panels_height(mode: GameMode) -> u16 {
+    announcement_y(mode) + MAX_LABELS
+}
+
+//Sprint counts up and ultra counts down, marathon has no clock
+fn shows_time(mode: GameMode) -> bool {
+    !mode.levels_up()
+}
+
+fn announcement_y(mode: GameMode) -> u16 {
+    if shows_time(mode) {
+        18
+    } else {
+        15
+    }
+}
+
+//Minutes, seconds and tenths like 2:05.3
+pub fn format_time(frames: u64) -> String {
+    let tenths = frames * 10 / FRAMES_PER_SECOND as u64;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+//The ghost is drawn first, ghost_distance rows below, so the piece covers it when they overlap
+fn render_current_piece(
+    tetromino: &[String],
+    screen: &mut Screen,
+    piece: &TetrisShape,
+    ghost_distance: Option<i16>,
+) {
+    let size = piece.size();
+    if let Some(distance) = ghost_distance {
+        for px in 0..size {
+            for py in 0..size {
+                let char_as_bytes: u8 = tetromino[piece.current_piece as usize].as_bytes()
+                    [Rotation::rotate(px, py, size, &piece.current_rotation) as usize];
+                if char_as_bytes as char == 'X' {
+                    let this_y = (piece.current_y + distance + py + 2) as u16;
+                    let this_x = (piece.current_x + px + 2) as u16;
+                    screen.add_directly('░', piece.current_color, Color::Black, this_x, this_y)
+                }
+            }
+        }
+    }
+    for px in 0..size {
+        for py in 0..size {
+            let char_as_bytes: u8 = tetromino[piece.current_piece as usize].as_bytes()
+                [Rotation::rotate(px, py, size, &piece.current_rotation) as usize];
+            if char_as_bytes as char == 'X' {
+                let this_y = (piece.current_y + py + 2) as u16;
+                let this_x = (piece.current_x + px + 2) as u16;
+                screen.add_directly('0', Color::Grey, piece.current_color, this_x, this_y)
+            }
+        }
+    }
+}
+
+//Draws a piece in its spawn orientation with the top left of its box at pos_x, pos_y
+fn render_piece_preview(
+    tetromino: &[String],
+    screen: &mut Screen,
+    piece: i16,
+    color: Color,
+    pos_x: u16,
+    pos_y: u16,
+) {
+    for px in 0..4 {
+        for py in 0..2 {
+            let char_as_bytes: u8 = tetromino[piece as usize].as_bytes()
+                [Rotation::rotate(px, py, 4, &Rotation::R0) as usize];
+            if char_as_bytes as char == 'X' {
+                screen.add_directly(
+                    '0',
+                    Color::Grey,
+                    color,
+                    pos_x + px as u16,
+                    pos_y + py as u16,
+                )
+            }
+        }
+    }
+}
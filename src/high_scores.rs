@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Result};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tetris::engine::GameMode;
+
+use crate::storage::write_atomically;
+
+pub const MAX_ENTRIES: usize = 10;
+pub const NAME_LENGTH: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    pub frames: u64,
+    pub date: String,
+    #[serde(with = "crate::storage::seed")]
+    pub seed: u64,
+}
+
+//One table per mode name, best entry first
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct HighScores {
+    tables: BTreeMap<String, Vec<ScoreEntry>>,
+}
+
+impl HighScores {
+    //A missing file is an empty table, one that can not be read or parsed is moved
+    //aside so the next save does not overwrite it
+    pub fn load() -> HighScores {
+        let path = match scores_path() {
+            Some(path) => path,
+            None => return HighScores::default(),
+        };
+        let parsed = match fs::read_to_string(&path) {
+            Ok(text) => from_text(&text),
+            Err(error) if error.kind() == ErrorKind::NotFound => return HighScores::default(),
+            Err(_) => None,
+        };
+        parsed.unwrap_or_else(|| {
+            let _ = fs::rename(&path, path.with_extension("toml.corrupt"));
+            HighScores::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = scores_path().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no data directory")
+        })?;
+        let text = toml::to_string(self)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        write_atomically(&path, &text)
+    }
+
+    pub fn entries(&self, mode: GameMode) -> &[ScoreEntry] {
+        self.tables
+            .get(mode.name())
+            .map(|entries| entries.as_slice())
+            .unwrap_or(&[])
+    }
+
+    //The place the entry would get in the table, None if it does not make it
+    pub fn rank(&self, mode: GameMode, entry: &ScoreEntry) -> Option<usize> {
+        let entries = self.entries(mode);
+        let rank = entries
+            .iter()
+            .position(|other| is_better(mode, entry, other))
+            .unwrap_or(entries.len());
+        if rank < MAX_ENTRIES {
+            Some(rank)
+        } else {
+            None
+        }
+    }
+
+    pub fn insert(&mut self, mode: GameMode, entry: ScoreEntry) {
+        if let Some(rank) = self.rank(mode, &entry) {
+            let entries = self.tables.entry(String::from(mode.name())).or_default();
+            entries.insert(rank, entry);
+            entries.truncate(MAX_ENTRIES);
+        }
+    }
+}
+
+//A hand edited file may be out of order or too long, the tables are put back in
+//shape so ranking and inserting can rely on them. Unknown modes are ranked by score.
+fn from_text(text: &str) -> Option<HighScores> {
+    let mut high_scores: HighScores = toml::from_str(text).ok()?;
+    for (name, entries) in high_scores.tables.iter_mut() {
+        let mode = GameMode::from_name(name).unwrap_or(GameMode::Marathon);
+        entries.sort_by(|entry, other| compare(mode, entry, other));
+        entries.truncate(MAX_ENTRIES);
+    }
+    Some(high_scores)
+}
+
+fn is_better(mode: GameMode, entry: &ScoreEntry, other: &ScoreEntry) -> bool {
+    compare(mode, entry, other) == Ordering::Less
+}
+
+//Better entries come first. Sprint is a race so the fastest time wins, the other
+//modes go by score.
+fn compare(mode: GameMode, entry: &ScoreEntry, other: &ScoreEntry) -> Ordering {
+    if mode.line_goal().is_some() {
+        entry.frames.cmp(&other.frames)
+    } else {
+        other.score.cmp(&entry.score)
+    }
+}
+
+fn scores_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|directory| directory.join("termtris").join("scores.toml"))
+}
+
+//The current UTC date as YYYY-MM-DD
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+//Days since 1970-01-01 to a calendar date, from Howard Hinnant's date algorithms
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32, frames: u64) -> ScoreEntry {
+        ScoreEntry {
+            name: String::from("ABC"),
+            score,
+            lines: 40,
+            level: 1,
+            frames,
+            date: String::from("2024-01-01"),
+            seed: 1,
+        }
+    }
+
+    //The file as it would look with the entries in the order given
+    fn file_text(mode: GameMode, entries: Vec<ScoreEntry>) -> String {
+        let mut high_scores = HighScores::default();
+        high_scores
+            .tables
+            .insert(String::from(mode.name()), entries);
+        toml::to_string(&high_scores).unwrap()
+    }
+
+    fn scores(high_scores: &HighScores, mode: GameMode) -> Vec<u32> {
+        high_scores
+            .entries(mode)
+            .iter()
+            .map(|entry| entry.score)
+            .collect()
+    }
+
+    #[test]
+    fn a_hand_edited_table_is_sorted_and_cut_to_size() {
+        let entries = (1..=14).map(|score| entry(score * 100, 0)).collect();
+        let high_scores = from_text(&file_text(GameMode::Marathon, entries)).unwrap();
+        let expected: Vec<u32> = (5..=14).rev().map(|score| score * 100).collect();
+        assert_eq!(scores(&high_scores, GameMode::Marathon), expected);
+    }
+
+    #[test]
+    fn sprint_tables_are_sorted_fastest_first() {
+        let entries = vec![entry(0, 5000), entry(0, 3000), entry(0, 4000)];
+        let high_scores = from_text(&file_text(GameMode::Sprint, entries)).unwrap();
+        let frames: Vec<u64> = high_scores
+            .entries(GameMode::Sprint)
+            .iter()
+            .map(|entry| entry.frames)
+            .collect();
+        assert_eq!(frames, [3000, 4000, 5000]);
+    }
+
+    #[test]
+    fn a_new_entry_goes_in_its_place_after_loading() {
+        let entries = vec![entry(100, 0), entry(300, 0)];
+        let mut high_scores = from_text(&file_text(GameMode::Ultra, entries)).unwrap();
+        assert_eq!(high_scores.rank(GameMode::Ultra, &entry(200, 0)), Some(1));
+        high_scores.insert(GameMode::Ultra, entry(200, 0));
+        assert_eq!(scores(&high_scores, GameMode::Ultra), [300, 200, 100]);
+    }
+
+    #[test]
+    fn a_damaged_file_is_not_read() {
+        assert!(from_text("[[marathon]]\nscore = \"lots\"\n").is_none());
+    }
+}
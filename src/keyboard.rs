@@ -0,0 +1,187 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::keys::KeyBinding;
+
+//Without release events a key counts as held once the terminal starts repeating it
+//and is let go when the repeats stop. The first timeout has to outlast the
+//key repeat delay of the OS, the second one the gap between two repeats.
+const REPEAT_START_TIMEOUT: Duration = Duration::from_millis(600);
+const REPEAT_TIMEOUT: Duration = Duration::from_millis(150);
+
+//Keys are held by their code, the modifiers are the ones it was pressed with
+struct KeyHold {
+    binding: KeyBinding,
+    last_seen: Instant,
+    repeating: bool,
+}
+
+pub struct Keyboard {
+    reports_release: bool,
+    held: HashMap<KeyCode, KeyHold>,
+    pressed: Vec<KeyBinding>,
+}
+
+impl Keyboard {
+    //An enhanced keyboard reports when keys are let go
+    pub fn new(enhanced: bool) -> Keyboard {
+        Keyboard {
+            //The Windows console always reports releases
+            reports_release: enhanced || cfg!(windows),
+            held: HashMap::new(),
+            pressed: Vec::new(),
+        }
+    }
+
+    //Forgets the presses once a game step has seen them
+    pub fn clear_presses(&mut self) {
+        self.pressed.clear();
+    }
+
+    //Lets go of keys that stopped repeating
+    pub fn expire_holds(&mut self) {
+        self.expire_holds_at(Instant::now());
+    }
+
+    fn expire_holds_at(&mut self, now: Instant) {
+        if self.reports_release {
+            return;
+        }
+        self.held.retain(|_code, hold| {
+            let timeout = if hold.repeating {
+                REPEAT_TIMEOUT
+            } else {
+                REPEAT_START_TIMEOUT
+            };
+            now.duration_since(hold.last_seen) < timeout
+        });
+    }
+
+    //Forgets every key, used when events may have been missed
+    pub fn release_all(&mut self) {
+        self.held.clear();
+        self.pressed.clear();
+    }
+
+    pub fn handle(&mut self, event: KeyEvent) {
+        self.handle_at(event, Instant::now());
+    }
+
+    fn handle_at(&mut self, event: KeyEvent, now: Instant) {
+        let binding = KeyBinding::from_event(&event);
+        match event.kind {
+            KeyEventKind::Press => {
+                //Without release events a press of a key that is still held is the OS
+                //repeating it, it still counts as a press like it did before
+                let repeating = self.reports_release || self.held.contains_key(&binding.code);
+                self.held.insert(
+                    binding.code,
+                    KeyHold {
+                        binding,
+                        last_seen: now,
+                        repeating,
+                    },
+                );
+                self.pressed.push(binding);
+            }
+            KeyEventKind::Repeat => (),
+            KeyEventKind::Release => {
+                self.held.remove(&binding.code);
+            }
+        }
+    }
+
+    //A tap without release events is only held for the frame it arrived in
+    pub fn is_held(&self, binding: KeyBinding) -> bool {
+        match self.held.get(&binding.code) {
+            Some(hold) if hold.binding == binding => {
+                hold.repeating || self.pressed.contains(&binding)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn was_pressed(&self, binding: KeyBinding) -> bool {
+        self.pressed.contains(&binding)
+    }
+
+    pub fn is_any_held(&self, bindings: &[KeyBinding]) -> bool {
+        bindings.iter().any(|binding| self.is_held(*binding))
+    }
+
+    pub fn was_any_pressed(&self, bindings: &[KeyBinding]) -> bool {
+        bindings.iter().any(|binding| self.was_pressed(*binding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn event(code: KeyCode, kind: KeyEventKind) -> KeyEvent {
+        KeyEvent::new_with_kind(code, KeyModifiers::NONE, kind)
+    }
+
+    //Set directly, Windows would always report releases
+    fn keyboard(reports_release: bool) -> Keyboard {
+        let mut keyboard = Keyboard::new(reports_release);
+        keyboard.reports_release = reports_release;
+        keyboard
+    }
+
+    fn left() -> KeyBinding {
+        KeyBinding::new(KeyCode::Left, KeyModifiers::NONE)
+    }
+
+    fn millis(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn a_tap_without_release_events_is_held_for_one_frame() {
+        let mut keyboard = keyboard(false);
+        let start = Instant::now();
+        keyboard.handle_at(event(KeyCode::Left, KeyEventKind::Press), start);
+        assert!(keyboard.is_held(left()) && keyboard.was_pressed(left()));
+        keyboard.clear_presses();
+        assert!(!keyboard.is_held(left()));
+        //Let go once the repeat delay of the OS passed without a repeat
+        keyboard.expire_holds_at(millis(start, 599));
+        assert!(keyboard.held.contains_key(&KeyCode::Left));
+        keyboard.expire_holds_at(millis(start, 600));
+        assert!(keyboard.held.is_empty());
+    }
+
+    #[test]
+    fn a_repeating_key_is_held_until_the_repeats_stop() {
+        let mut keyboard = keyboard(false);
+        let start = Instant::now();
+        keyboard.handle_at(event(KeyCode::Left, KeyEventKind::Press), start);
+        keyboard.handle_at(
+            event(KeyCode::Left, KeyEventKind::Press),
+            millis(start, 500),
+        );
+        keyboard.clear_presses();
+        keyboard.expire_holds_at(millis(start, 649));
+        assert!(keyboard.is_held(left()));
+        keyboard.expire_holds_at(millis(start, 650));
+        assert!(!keyboard.is_held(left()));
+    }
+
+    #[test]
+    fn release_events_end_the_hold_and_timeouts_are_not_used() {
+        let mut keyboard = keyboard(true);
+        let start = Instant::now();
+        keyboard.handle_at(event(KeyCode::Left, KeyEventKind::Press), start);
+        keyboard.clear_presses();
+        keyboard.expire_holds_at(millis(start, 10_000));
+        assert!(keyboard.is_held(left()));
+        keyboard.handle_at(
+            event(KeyCode::Left, KeyEventKind::Release),
+            millis(start, 10_001),
+        );
+        assert!(!keyboard.is_held(left()));
+    }
+}
@@ -0,0 +1,134 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+//Only these modifiers can be part of a binding, the rest are ignored
+const MODIFIERS: [(&str, KeyModifiers); 3] = [
+    ("Ctrl", KeyModifiers::CONTROL),
+    ("Alt", KeyModifiers::ALT),
+    ("Shift", KeyModifiers::SHIFT),
+];
+
+const NAMED_KEYS: [(&str, KeyCode); 15] = [
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Space", KeyCode::Char(' ')),
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+];
+
+//Names like "Left", "Space", "F5" or a single character, case only matters for characters
+fn parse_key(name: &str) -> Option<KeyCode> {
+    if let Some((_, code)) = NAMED_KEYS
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+    {
+        return Some(*code);
+    }
+    let mut characters = name.chars();
+    if let (Some(character), None) = (characters.next(), characters.next()) {
+        return Some(KeyCode::Char(character));
+    }
+    let number = name.strip_prefix('F').or_else(|| name.strip_prefix('f'))?;
+    match number.parse() {
+        Ok(number) if (1..=12).contains(&number) => Some(KeyCode::F(number)),
+        _ => None,
+    }
+}
+
+fn key_name(code: KeyCode) -> String {
+    if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, other)| *other == code) {
+        return String::from(*name);
+    }
+    match code {
+        KeyCode::Char(character) => character.to_string(),
+        KeyCode::F(number) => format!("F{}", number),
+        _ => String::from("?"),
+    }
+}
+
+//A key together with the modifiers that have to be held with it
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    //Terminals disagree on whether a capital letter comes with shift, so letters always
+    //store shift as a modifier of the small letter and other characters never do
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(character) if character.is_alphabetic() => {
+                if character.is_uppercase() {
+                    modifiers |= KeyModifiers::SHIFT;
+                }
+                KeyCode::Char(character.to_lowercase().next().unwrap_or(character))
+            }
+            KeyCode::Char(character) => {
+                modifiers -= KeyModifiers::SHIFT;
+                KeyCode::Char(character)
+            }
+            //Shift+Tab arrives as its own key
+            KeyCode::BackTab => {
+                modifiers |= KeyModifiers::SHIFT;
+                KeyCode::Tab
+            }
+            code => code,
+        };
+        KeyBinding { code, modifiers }
+    }
+
+    pub fn from_event(event: &KeyEvent) -> KeyBinding {
+        KeyBinding::new(event.code, event.modifiers)
+    }
+
+    //Modifiers are written in front like "Ctrl+Left", a capital letter means shift
+    pub fn parse(name: &str) -> Option<KeyBinding> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = name;
+        'prefixes: loop {
+            for (prefix, modifier) in MODIFIERS.iter() {
+                let matches = rest.len() > prefix.len() + 1
+                    && rest[..prefix.len()].eq_ignore_ascii_case(prefix)
+                    && rest[prefix.len()..].starts_with('+');
+                if matches {
+                    modifiers |= *modifier;
+                    rest = &rest[prefix.len() + 1..];
+                    continue 'prefixes;
+                }
+            }
+            break;
+        }
+        parse_key(rest).map(|code| KeyBinding::new(code, modifiers))
+    }
+
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        for (prefix, modifier) in MODIFIERS.iter() {
+            let shown_as_capital = *modifier == KeyModifiers::SHIFT
+                && matches!(self.code, KeyCode::Char(character) if character.is_alphabetic());
+            if self.modifiers.contains(*modifier) && !shown_as_capital {
+                name.push_str(prefix);
+                name.push('+');
+            }
+        }
+        match self.code {
+            KeyCode::Char(character) if self.modifiers.contains(KeyModifiers::SHIFT) => {
+                name.extend(character.to_uppercase())
+            }
+            code => name.push_str(&key_name(code)),
+        }
+        name
+    }
+}
@@ -1,551 +1,570 @@
 use crossterm::{
-    cursor::{Hide, MoveTo, Show},
     event::{poll, read, Event, KeyCode},
-    execute,
-    style::{Color, Print},
-    terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, SetSize, SetTitle},
-    Result,
+    style::Color,
+    terminal::size,
 };
-use rand::Rng;
-use std::io::stdout;
-use std::{thread, time};
+use std::io::Result;
+use std::time::{Duration, Instant};
 
-mod render;
-mod rotation;
-use render::Block;
-use render::Frame;
-use render::FrameStyle;
-use render::Screen;
-use render::Text;
-use rotation::Rotation;
+mod bench;
+mod cli;
+mod config;
+mod controls;
+mod controls_screen;
+mod game_view;
+mod high_scores;
+mod keyboard;
+mod keys;
+mod main_menu;
+mod menu;
+mod playback;
+mod replay;
+mod save_game;
+mod settings_screen;
+mod storage;
+mod terminal;
+mod ui;
+use clap::Parser;
+use cli::{Cli, Command};
+use config::Config;
+use controls::{Action, Controls};
+use game_view::format_time;
+use game_view::GameView;
+use high_scores::HighScores;
+use high_scores::ScoreEntry;
+use high_scores::NAME_LENGTH;
+use keyboard::Keyboard;
+use keys::KeyBinding;
+use main_menu::main_menu;
+use main_menu::MenuChoice;
+use menu::Menu;
+use playback::play_replay;
+use replay::Replay;
+use terminal::TerminalGuard;
 
-struct Input {
-    right: bool,
-    left: bool,
-    down: bool,
-    rotate: bool,
-}
+use tetris::engine::build_tetromino;
+use tetris::engine::Game;
+use tetris::engine::GameEvent;
+use tetris::engine::GameSettings;
+use tetris::engine::Input;
+use tetris::engine::FRAMES_PER_SECOND;
+use tetris::render::screen::ScreenElement;
+use tetris::render::Frame;
+use tetris::render::FrameStyle;
+use tetris::render::Screen;
+use tetris::render::Text;
 
-impl Input {
-    fn new() -> Input {
-        Input {
-            right: false,
-            left: false,
-            down: false,
-            rotate: false,
-        }
-    }
-}
+//Frames of game logic run in a row at most after the loop fell behind
+const MAX_CATCH_UP_FRAMES: u32 = 5;
 
 struct ScreenSetting {
-    field_width: i16,
-    field_height: i16,
     screen_width: i16,
     screen_height: i16,
+    show_ghost: bool,
+    fixed_seed: bool,
 }
 
 impl ScreenSetting {
-    fn new(
-        field_width: i16,
-        field_height: i16,
-        screen_width: i16,
-        screen_height: i16,
-    ) -> ScreenSetting {
+    fn new(screen_width: i16, screen_height: i16) -> ScreenSetting {
         ScreenSetting {
-            field_width,
-            field_height,
             screen_height,
             screen_width,
+            show_ghost: true,
+            fixed_seed: false,
         }
     }
 }
 
-struct TetrisShape {
-    current_piece: i16,
-    current_rotation: Rotation,
-    current_color: Color,
-    current_x: i16,
-    current_y: i16,
-}
-
-impl TetrisShape {
-    fn new(current_x: i16, current_y: i16) -> TetrisShape {
-        let random_value = rand::thread_rng().gen_range(0..7);
-        let piece_colors = [
-            Color::Cyan,
-            Color::Green,
-            Color::Blue,
-            Color::Yellow,
-            Color::Magenta,
-            Color::Red,
-            Color::Green,
-        ];
-        TetrisShape {
-            current_piece: random_value,
-            current_color: piece_colors[random_value as usize],
-            current_rotation: Rotation::R0,
-            current_x,
-            current_y,
-        }
-    }
+//The panels sit right of the field and the next panel grows by three rows per preview
+fn fit_layout(screen_settings: &mut ScreenSetting, game_settings: &GameSettings) {
+    screen_settings.screen_width = 34.max(game_settings.field_width + 22);
+    screen_settings.screen_height = (game_view::panels_height(game_settings.mode) as i16)
+        .max(game_settings.field_height + 2)
+        .max(6 + 3 * game_settings.preview_count as i16);
 }
 
 fn main() -> Result<()> {
     //Startup=================================================================
     let tetromino = build_tetromino();
-    let screen_settings = ScreenSetting::new(12, 18, 34, 20);
-    execute!(
-        stdout(),
-        EnterAlternateScreen,
-        SetSize(
-            screen_settings.screen_width as u16,
-            screen_settings.screen_height as u16
-        ),
-        SetTitle("Tetris"),
-        Clear(ClearType::All),
-        Hide,
+    let mut game_settings = GameSettings::new(12, 18);
+    let mut screen_settings = ScreenSetting::new(34, 20);
+    let mut controls = Controls::default();
+    let cli = Cli::parse();
+    //The config file is read first so the command line can override it
+    let mut config = Config::load(cli.config.clone()).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        std::process::exit(2);
+    });
+    let configured = config.apply(&mut game_settings, &mut screen_settings, &mut controls);
+    if let Err(message) = configured {
+        eprintln!("{}", message);
+        std::process::exit(2);
+    }
+    cli.apply(&mut game_settings, &mut screen_settings);
+    match cli.command {
+        Some(Command::Scores { mode }) => {
+            cli::print_scores(&HighScores::load(), mode);
+            return Ok(());
+        }
+        Some(Command::Bench { frames }) => {
+            fit_layout(&mut screen_settings, &game_settings);
+            bench::run(tetromino, game_settings, &screen_settings, frames);
+            return Ok(());
+        }
+        None => (),
+    }
+    //A replay brings its own settings, only the colors and the ghost are still ours
+    let replay = cli.replay.as_ref().map(|path| {
+        Replay::load(path).unwrap_or_else(|message| {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        })
+    });
+    if let Some(replay) = &replay {
+        replay.apply(&mut game_settings);
+    }
+    fit_layout(&mut screen_settings, &game_settings);
+    let terminal = TerminalGuard::new(
+        screen_settings.screen_width as u16,
+        screen_settings.screen_height as u16,
     )?;
+    let mut keyboard = Keyboard::new(terminal.keyboard_enhanced());
 
     //Run=====================================================================
 
-    intro()?;
-    run_game(tetromino, &screen_settings)?;
+    let (terminal_width, terminal_height) = size()?;
+    let mut screen = Screen::new(terminal_width, terminal_height);
+    screen.set_ascii(cli.ascii);
+    screen.set_colors(!cli.no_color);
+    if let Some(replay) = &replay {
+        play_replay(
+            tetromino,
+            &mut screen,
+            &screen_settings,
+            &game_settings,
+            &mut keyboard,
+            replay,
+        )?;
+        drop(terminal);
+        return Ok(());
+    }
+    let mut high_scores = HighScores::load();
+    //A mode from the command line skips the main menu the first time
+    let mut start_mode = cli.mode;
+    'menu: loop {
+        let choice = match start_mode.take() {
+            Some(mode) => MenuChoice::Play(mode),
+            None => match main_menu(
+                &mut screen,
+                &mut screen_settings,
+                &mut game_settings,
+                &mut config,
+                &mut controls,
+                &high_scores,
+            )? {
+                Some(choice) => choice,
+                None => break,
+            },
+        };
+        //A continued game brings its settings along, restarting it plays the same kind of game,
+        //they only go into a copy so the session goes back to its own settings afterwards
+        let mut played_settings = game_settings.clone();
+        let mut saved_game = match choice {
+            MenuChoice::Play(mode) => {
+                game_settings.mode = mode;
+                played_settings.mode = mode;
+                fit_layout(&mut screen_settings, &played_settings);
+                None
+            }
+            MenuChoice::Continue(saved_game) => {
+                saved_game.replay.apply(&mut played_settings);
+                fit_layout(&mut screen_settings, &played_settings);
+                Some(saved_game)
+            }
+        };
+        loop {
+            let (game, replay) = match saved_game.take() {
+                Some(saved_game) => (saved_game.game, saved_game.replay),
+                None => {
+                    //A seed from the command line is kept so the same game can be tried again
+                    if !screen_settings.fixed_seed {
+                        played_settings.seed = rand::random();
+                    }
+                    (
+                        Game::new(tetromino.clone(), &played_settings),
+                        Replay::new(&played_settings),
+                    )
+                }
+            };
+            let exit = run_game(
+                game,
+                replay,
+                &mut screen,
+                &screen_settings,
+                &controls,
+                &mut keyboard,
+                &mut high_scores,
+            )?;
+            match exit {
+                GameExit::Restart => (),
+                GameExit::MainMenu => break,
+                GameExit::Quit => break 'menu,
+            }
+        }
+        fit_layout(&mut screen_settings, &game_settings);
+    }
 
     //Exit=================================================================
 
-    quit()?;
+    drop(terminal);
     Ok(())
 }
 
-fn quit() -> Result<()> {
-    execute!(stdout(), LeaveAlternateScreen, Show,)?;
-    Ok(())
+//How a game was left, picked on the game over screen or by quitting
+#[derive(Clone, Copy, PartialEq)]
+enum GameExit {
+    Restart,
+    MainMenu,
+    Quit,
 }
 
-fn run_game(tetromino: Vec<String>, screen_settings: &ScreenSetting) -> Result<()> {
-    let mut field = create_initial_field(screen_settings);
-    let mut piece = TetrisShape::new(screen_settings.field_width / 2, 0);
+//Plays a new game or one that was continued, the replay carries on from where the game is
+fn run_game(
+    mut game: Game,
+    mut replay: Replay,
+    screen: &mut Screen,
+    screen_settings: &ScreenSetting,
+    controls: &Controls,
+    keyboard: &mut Keyboard,
+    high_scores: &mut HighScores,
+) -> Result<GameExit> {
+    let view = GameView::new(&game);
     let mut input_state = Input::new();
-    let duration = time::Duration::from_millis(50);
-    let mut ticks = 1;
-    let mut handicap = 20;
-    let mut lines: Vec<i16> = Vec::new();
-    let mut points = 0;
-    let mut game_over = false;
-    let mut pieces_spawned = 0;
-    let score = Frame::new(19, 3, FrameStyle::DoubleLine, Color::White, Color::Black);
-    let mut screen = Screen::new(
-        screen_settings.screen_width as u16,
-        screen_settings.screen_height as u16,
-    );
-    let score_title = Text::new(String::from(" SCORE "), Color::Cyan, Color::Black);
-    while !game_over {
-        set_input(&mut input_state, &mut game_over)?;
-        move_shape(
-            &mut input_state,
-            &mut piece,
-            &tetromino,
-            screen_settings,
-            &field,
-        );
-        if ticks % handicap == 0 {
-            piece = move_down(
-                piece,
-                &tetromino,
-                screen_settings,
-                &mut field,
-                &mut lines,
-                &mut game_over,
-                &mut handicap,
-                &mut pieces_spawned,
-            );
+    let frame_duration = Duration::from_secs(1) / FRAMES_PER_SECOND;
+    let mut pause_requested = false;
+    let mut fits = ui::center_layout(screen, screen_settings);
+    let announcement_frames = 2 * FRAMES_PER_SECOND;
+    let mut announcement: Vec<String> = Vec::new();
+    let mut announcement_timer = 0;
+    let mut last_time = Instant::now();
+    let mut lag = Duration::ZERO;
+    let mut redraw = true;
+    let mut exit = None;
+    terminal::set_save_on_interrupt(true);
+    'game: while !game.is_game_over() {
+        let terminal_events = read_events(keyboard)?;
+        //Ctrl+C or a signal, the game is saved below and then the program leaves
+        if terminal::interrupted().is_some() {
+            exit = Some(GameExit::Quit);
+            break;
         }
-        screen.begin_render();
-        screen.add_element_at(&field, 2, 2);
-        screen.add_element_at(&score, 15, 2);
-        screen.add_element_at(&score_title, 21, 2);
-        let score = format!("{:0>11}", points);
-        screen.add_string_at(score, Color::DarkBlue, Color::Black, 19, 3);
-        render_current_piece(&tetromino, &mut screen, &piece);
-        screen.end_render()?;
-        ticks += 1;
-        thread::sleep(duration);
-        points += add_points_to_score(&mut lines, screen_settings, &mut field);
-    }
-    Ok(())
-}
-
-fn intro() -> Result<()> {
-    execute!(stdout(), MoveTo(0, 5), Print("Instructions:\nUse arrow keys to move, up to rotate.\n\nWhen you are done ESC to quit\n\nPress any key to continue"))?;
-    loop {
-        match read()? {
-            Event::Key(_event) => return Ok(()),
-            Event::Mouse(_event) => (),
-            Event::Resize(_width, _height) => (),
+        if let Some((width, height)) = terminal_events.resized {
+            screen.resize(width, height);
+            fits = ui::center_layout(screen, screen_settings);
+            redraw = true;
         }
-    }
-}
-
-fn render_current_piece(tetromino: &[String], screen: &mut Screen, piece: &TetrisShape) {
-    for px in 0..4 {
-        for py in 0..4 {
-            let char_as_bytes: u8 = tetromino[piece.current_piece as usize].as_bytes()
-                [Rotation::rotate(px, py, &piece.current_rotation) as usize];
-            if char_as_bytes as char == 'X' {
-                let this_y = (piece.current_y + py + 2) as u16;
-                let this_x = (piece.current_x + px + 2) as u16;
-                screen.add_directly('0', Color::Grey, piece.current_color, this_x, this_y)
+        if terminal_events.focus_lost {
+            pause_requested = true;
+        }
+        if pause_requested {
+            pause_requested = false;
+            exit = pause_menu(screen, screen_settings, controls, &view, &game)?;
+            if exit.is_some() {
+                break;
             }
+            //Keys let go of while paused never reported their release to the game
+            keyboard.release_all();
+            fits = ui::center_layout(screen, screen_settings);
+            last_time = Instant::now();
+            lag = Duration::ZERO;
+            redraw = true;
+            continue;
         }
-    }
-}
-
-fn add_points_to_score(
-    lines: &mut Vec<i16>,
-    screen_settings: &ScreenSetting,
-    field: &mut Block,
-) -> u16 {
-    if !lines.is_empty() {
-        let score_duration = time::Duration::from_millis(400);
-        thread::sleep(score_duration);
-        for elem in lines.to_owned() {
-            for px in 1..screen_settings.field_width - 1 {
-                field.change_content(px as u16, elem as u16, ' ', Color::Black, Color::Black);
-                for py in (1..elem + 1).rev() {
-                    let index = ((py - 1) * screen_settings.field_width + px) as usize;
-                    let new_char = *field.get_content_by_index(index);
-                    let new_color = *field.get_background_color_by_index(index);
-                    field.change_content(px as u16, py as u16, new_char, Color::Grey, new_color);
-                }
-                field.change_content(px as u16, 0, ' ', Color::Black, Color::Black);
+        //The logic runs at a fixed rate however long rendering took, after a long
+        //stall only a few frames are caught up so the game does not jump ahead
+        let now = Instant::now();
+        lag = (lag + (now - last_time)).min(frame_duration * MAX_CATCH_UP_FRAMES);
+        last_time = now;
+        if !fits {
+            //The game waits while it cannot be seen, it can still be paused
+            lag = Duration::ZERO;
+            pause_requested = keyboard.was_any_pressed(controls.keys(Action::Pause));
+            keyboard.clear_presses();
+            if redraw {
+                redraw = false;
+                ui::render_too_small(screen, screen_settings)?;
             }
+            poll(frame_duration)?;
+            continue;
         }
-        let line_num = lines.len() as u16;
-        lines.clear();
-        return line_num * 100 + (line_num * 50);
-    }
-    0
-}
-
-fn set_input(input_state: &mut Input, game_over: &mut bool) -> Result<()> {
-    input_state.down = false;
-    input_state.left = false;
-    input_state.right = false;
-    input_state.rotate = false;
-    if poll(time::Duration::from_millis(0))? {
-        match read()? {
-            Event::Key(input_event) => {
-                if input_event.code == KeyCode::Left {
-                    input_state.left = true;
-                    input_state.right = false;
-                }
-                if input_event.code == KeyCode::Right {
-                    input_state.right = true;
-                    input_state.left = false;
-                }
-                if input_event.code == KeyCode::Down {
-                    input_state.down = true
-                }
-                if input_event.code == KeyCode::Up {
-                    input_state.rotate = true
-                }
-                if input_event.code == KeyCode::Esc {
-                    *game_over = true
+        while lag >= frame_duration && !game.is_game_over() && !pause_requested {
+            lag -= frame_duration;
+            keyboard.expire_holds();
+            set_input(&mut input_state, keyboard, controls);
+            if keyboard.was_any_pressed(controls.keys(Action::Quit)) {
+                exit = Some(GameExit::Quit);
+                break 'game;
+            }
+            pause_requested |= keyboard.was_any_pressed(controls.keys(Action::Pause));
+            keyboard.clear_presses();
+            replay.record(&input_state);
+            for event in game.step(&input_state) {
+                if let GameEvent::Scored(score_event) = event {
+                    announcement = score_event.labels();
+                    announcement_timer = announcement_frames;
                 }
             }
-            Event::Mouse(_event) => (),
-            Event::Resize(_width, _height) => (),
+            announcement_timer = announcement_timer.saturating_sub(1);
+            redraw = true;
         }
-    }
-    Ok(())
-}
-
-fn move_down(
-    mut p_shape: TetrisShape,
-    p_tetromino: &[String],
-    p_screen: &ScreenSetting,
-    p_field: &mut Block,
-    p_lines: &mut Vec<i16>,
-    game_over: &mut bool,
-    difficulty_handicap: &mut u16,
-    pieces_spawned: &mut u16,
-) -> TetrisShape {
-    if does_piece_fit(
-        p_tetromino,
-        p_shape.current_piece,
-        &p_shape.current_rotation,
-        p_shape.current_x,
-        p_shape.current_y + 1,
-        p_screen,
-        p_field,
-    ) {
-        p_shape.current_y += 1;
-        return p_shape;
-    } else {
-        lock_piece(&p_shape, p_tetromino, p_field);
-
-        test_full_lines(&p_shape, p_screen, p_field, p_lines);
-
-        //new piece and gameover
-        p_shape = TetrisShape::new(p_screen.field_width / 2, 0);
-
-        *pieces_spawned += 1;
-        if *pieces_spawned % 10 == 0 && *difficulty_handicap > 5 {
-            *difficulty_handicap -= 1;
+        if !redraw {
+            //Sleeps until the next step is due unless a key arrives first
+            poll(frame_duration - lag)?;
+            continue;
         }
-        *game_over = !does_piece_fit(
-            p_tetromino,
-            p_shape.current_piece,
-            &p_shape.current_rotation,
-            p_shape.current_x,
-            p_shape.current_y,
-            p_screen,
-            p_field,
-        );
-    }
-    p_shape
-}
-
-fn lock_piece(p_shape: &TetrisShape, p_tetromino: &[String], p_field: &mut Block) {
-    for px in 0..4 {
-        for py in 0..4 {
-            let char_as_bytes: u8 = p_tetromino[p_shape.current_piece as usize].as_bytes()
-                [Rotation::rotate(px, py, &p_shape.current_rotation) as usize];
-            if char_as_bytes as char == 'X' {
-                p_field.change_content(
-                    (p_shape.current_x + px) as u16,
-                    (p_shape.current_y + py) as u16,
-                    '0',
-                    Color::Grey,
-                    p_shape.current_color,
-                );
-            }
+        redraw = false;
+        if announcement_timer == 0 {
+            announcement.clear();
         }
+        screen.begin_render();
+        view.render(screen, &game, screen_settings.show_ghost, &announcement);
+        screen.end_render()?;
     }
+    //A game that is quit halfway is saved to be continued and takes its replay along,
+    //every other game is kept as a replay, one that was left early can still show what went wrong
+    let suspended = exit == Some(GameExit::Quit)
+        && !game.is_game_over()
+        && game.frames_played() > 0
+        && save_game::save(&game, &replay).is_ok();
+    if !suspended && replay.frames() > 0 {
+        let _ = replay.save();
+    }
+    terminal::set_save_on_interrupt(false);
+    if let Some(code) = terminal::interrupted() {
+        terminal::exit(code);
+    }
+    if let Some(exit) = exit {
+        return Ok(exit);
+    }
+    //Keys still queued from the game should not end up in the name or pick an entry
+    while poll(Duration::ZERO)? {
+        read()?;
+    }
+    let saved = record_score(screen, screen_settings, &view, &game, high_scores)?;
+    game_over(screen, screen_settings, controls, &view, &game, saved)
 }
 
-fn test_full_lines(
-    p_shape: &TetrisShape,
-    p_screen: &ScreenSetting,
-    p_field: &mut Block,
-    p_lines: &mut Vec<i16>,
-) {
-    for py in 0..4 {
-        if p_shape.current_y + py < p_screen.field_height - 1 {
-            let mut line = true;
-            for px in 1..p_screen.field_width - 1 {
-                let index = ((p_shape.current_y + py) * p_screen.field_width + px) as usize;
-                line &= *p_field.get_content_by_index(index) != ' ';
+//Asks for initials when the game made it into the table, the message tells how saving went
+fn record_score(
+    screen: &mut Screen,
+    screen_settings: &ScreenSetting,
+    view: &GameView,
+    game: &Game,
+    high_scores: &mut HighScores,
+) -> Result<Option<String>> {
+    //A sprint only counts when all its lines were cleared
+    let finished = game.mode().line_goal().is_none() || game.is_goal_reached();
+    if !finished || game.score() == 0 {
+        return Ok(None);
+    }
+    let mut entry = ScoreEntry {
+        name: String::new(),
+        score: game.score(),
+        lines: game.lines_cleared(),
+        level: game.level(),
+        frames: game.frames_played(),
+        date: high_scores::today(),
+        seed: game.seed(),
+    };
+    let rank = match high_scores.rank(game.mode(), &entry) {
+        Some(rank) => rank,
+        None => return Ok(None),
+    };
+    let frame = Frame::new(30, 6, FrameStyle::DoubleLine, Color::White, Color::Black);
+    let title = Text::new(
+        String::from(" NEW HIGH SCORE "),
+        Color::Yellow,
+        Color::Black,
+    );
+    loop {
+        ui::show(screen, screen_settings, |screen| {
+            view.render(screen, game, false, &[]);
+            screen.add_element_at(&frame, 2, 7);
+            screen.add_element_at(&title, 9, 7);
+            let place = format!("Rank {} in {}", rank + 1, game.mode().name());
+            screen.add_string_at(place, Color::Cyan, Color::Black, 4, 8);
+            let name = format!("Initials {:_<3}", entry.name);
+            screen.add_string_at(name, Color::White, Color::Black, 4, 10);
+            let hint = String::from("Enter to save, Esc to skip");
+            screen.add_string_at(hint, Color::DarkGrey, Color::Black, 4, 11);
+        })?;
+        match ui::next_key(screen)? {
+            Some(KeyCode::Esc) => return Ok(None),
+            Some(KeyCode::Backspace) => {
+                entry.name.pop();
             }
-            if line {
-                for px in 1..p_screen.field_width - 1 {
-                    p_field.change_content(
-                        px as u16,
-                        (py + p_shape.current_y) as u16,
-                        '=',
-                        Color::Yellow,
-                        Color::Black,
-                    );
-                }
-                p_lines.push(p_shape.current_y + py);
+            Some(KeyCode::Enter) if !entry.name.is_empty() => break,
+            Some(KeyCode::Char(character))
+                if character.is_ascii_alphanumeric() && entry.name.len() < NAME_LENGTH =>
+            {
+                entry.name.push(character.to_ascii_uppercase());
             }
+            _ => (),
         }
     }
+    high_scores.insert(game.mode(), entry);
+    Ok(Some(match high_scores.save() {
+        Ok(()) => format!("Saved as rank {}", rank + 1),
+        Err(_) => String::from("Could not save the score"),
+    }))
 }
 
-fn move_shape(
-    input_state: &mut Input,
-    p_state: &mut TetrisShape,
-    p_tetromino: &[String],
-    p_screen: &ScreenSetting,
-    p_field: &Block,
-) {
-    if input_state.left
-        && does_piece_fit(
-            p_tetromino,
-            p_state.current_piece,
-            &p_state.current_rotation,
-            p_state.current_x - 1,
-            p_state.current_y,
-            p_screen,
-            p_field,
-        )
-    {
-        p_state.current_x -= 1;
-    }
-    if input_state.right
-        && does_piece_fit(
-            p_tetromino,
-            p_state.current_piece,
-            &p_state.current_rotation,
-            p_state.current_x + 1,
-            p_state.current_y,
-            p_screen,
-            p_field,
-        )
-    {
-        p_state.current_x += 1;
-    }
-    if input_state.down
-        && does_piece_fit(
-            p_tetromino,
-            p_state.current_piece,
-            &p_state.current_rotation,
-            p_state.current_x,
-            p_state.current_y + 1,
-            p_screen,
-            p_field,
-        )
-    {
-        p_state.current_y += 1;
-    }
-
-    if input_state.rotate {
-        let new_rotation = Rotation::rotate_clockwise(&p_state.current_rotation);
-        if does_piece_fit(
-            p_tetromino,
-            p_state.current_piece,
-            &new_rotation,
-            p_state.current_x,
-            p_state.current_y,
-            p_screen,
-            p_field,
-        ) {
-            p_state.current_rotation = new_rotation;
+//The field and the coming pieces stay hidden until the game is resumed, None resumes
+fn pause_menu(
+    screen: &mut Screen,
+    screen_settings: &ScreenSetting,
+    controls: &Controls,
+    view: &GameView,
+    game: &Game,
+) -> Result<Option<GameExit>> {
+    let frame = Frame::new(30, 7, FrameStyle::DoubleLine, Color::White, Color::Black);
+    let title = Text::new(String::from(" PAUSED "), Color::Yellow, Color::Black);
+    let mut menu = Menu::new(vec![
+        String::from("Resume"),
+        String::from("Restart"),
+        String::from("Quit"),
+    ]);
+    loop {
+        ui::show(screen, screen_settings, |screen| {
+            view.render_hidden(screen, game);
+            screen.add_element_at(&frame, 2, 7);
+            screen.add_element_at(&title, 13, 7);
+            menu.render(screen, 4, 9);
+        })?;
+        let event = match ui::next_key_event(screen)? {
+            Some(event) => event,
+            None if terminal::interrupted().is_some() => return Ok(Some(GameExit::Quit)),
+            None => continue,
+        };
+        let resume_key = controls.matches(Action::Pause, KeyBinding::from_event(&event));
+        match event.code {
+            _ if event.code == KeyCode::Esc || resume_key => return Ok(None),
+            _ if menu.handle(&event, controls) => {
+                return Ok(match menu.selected() {
+                    0 => None,
+                    1 => Some(GameExit::Restart),
+                    _ => Some(GameExit::Quit),
+                });
+            }
+            _ => (),
         }
     }
 }
 
-fn does_piece_fit(
-    p_tetromino: &[String],
-    p_tetrino: i16,
-    p_rotation: &Rotation,
-    p_pos_x: i16,
-    p_pos_y: i16,
-    p_screen: &ScreenSetting,
-    p_field: &Block,
-) -> bool {
-    for px in 0..4 {
-        for py in 0..4 {
-            let piece_index = Rotation::rotate(px, py, p_rotation);
-            let field_index = ((p_pos_y + py) * p_screen.field_width + (p_pos_x + px)) as usize;
-            if (p_pos_x + px) < p_screen.field_width
-                && (p_pos_y + py) < p_screen.field_height
-                && p_tetromino[p_tetrino as usize].as_bytes()[piece_index as usize] as char == 'X'
-                && *p_field.get_content_by_index(field_index) != ' '
-            {
-                return false;
+//Stays on top of the final field until Restart, Main Menu or Quit is picked
+fn game_over(
+    screen: &mut Screen,
+    screen_settings: &ScreenSetting,
+    controls: &Controls,
+    view: &GameView,
+    game: &Game,
+    saved: Option<String>,
+) -> Result<GameExit> {
+    let frame = Frame::new(30, 14, FrameStyle::DoubleLine, Color::White, Color::Black);
+    let title = if game.is_goal_reached() {
+        Text::new(String::from(" FINISHED "), Color::Green, Color::Black)
+    } else {
+        Text::new(String::from(" GAME OVER "), Color::Red, Color::Black)
+    };
+    let seconds = game.frames_played() as f64 / FRAMES_PER_SECOND as f64;
+    let pieces_per_second = if seconds > 0.0 {
+        game.pieces_placed() as f64 / seconds
+    } else {
+        0.0
+    };
+    let stats = [
+        format!("Score  {:>19}", game.score()),
+        format!("Lines  {:>19}", game.lines_cleared()),
+        format!("Level  {:>19}", game.level()),
+        format!("Pieces {:>19}", game.pieces_placed()),
+        format!("Time   {:>19}", format_time(game.frames_played())),
+        format!("PPS    {:>19.2}", pieces_per_second),
+        format!("Seed   {:>19}", game.seed()),
+    ];
+    let mut menu = Menu::new(vec![
+        String::from("Restart"),
+        String::from("Main Menu"),
+        String::from("Quit"),
+    ]);
+    loop {
+        ui::show(screen, screen_settings, |screen| {
+            view.render(screen, game, false, &[]);
+            screen.add_element_at(&frame, 2, 4);
+            screen.add_element_at(&title, 2 + (30 - title.get_width()) / 2, 4);
+            for (index, line) in stats.iter().enumerate() {
+                let pos_y = 5 + index as u16;
+                screen.add_string_at(line.clone(), Color::Cyan, Color::Black, 4, pos_y);
+            }
+            if let Some(message) = &saved {
+                screen.add_string_at(message.clone(), Color::Yellow, Color::Black, 4, 12);
+            }
+            menu.render(screen, 4, 13);
+        })?;
+        let event = match ui::next_key_event(screen)? {
+            Some(event) => event,
+            None => continue,
+        };
+        match event.code {
+            KeyCode::Esc => return Ok(GameExit::Quit),
+            _ if menu.handle(&event, controls) => {
+                return Ok(match menu.selected() {
+                    0 => GameExit::Restart,
+                    1 => GameExit::MainMenu,
+                    _ => GameExit::Quit,
+                });
             }
+            _ => (),
         }
     }
-    true
 }
 
-fn create_initial_field(screen_settings: &ScreenSetting) -> Block {
-    let characters = [' ', '║', '═', '╚', '╝', '╗', '╔'];
-    let mut field = Block::new(
-        screen_settings.field_width as u16,
-        screen_settings.field_height as u16,
-    );
-    for x in 0..screen_settings.field_width {
-        for y in 0..screen_settings.field_height {
-            if x == 0
-                || x == screen_settings.field_width - 1
-                || y == screen_settings.field_height - 1
-            {
-                if x == 0 && y == screen_settings.field_height - 1 {
-                    field.change_content(
-                        x as u16,
-                        y as u16,
-                        characters[3],
-                        Color::White,
-                        Color::Black,
-                    );
-                } else if x == screen_settings.field_width - 1
-                    && y == screen_settings.field_height - 1
-                {
-                    field.change_content(
-                        x as u16,
-                        y as u16,
-                        characters[4],
-                        Color::White,
-                        Color::Black,
-                    );
-                } else if y == screen_settings.field_height - 1 {
-                    field.change_content(
-                        x as u16,
-                        y as u16,
-                        characters[2],
-                        Color::White,
-                        Color::Black,
-                    );
-                } else if x == 0 || x == screen_settings.field_width - 1 {
-                    field.change_content(
-                        x as u16,
-                        y as u16,
-                        characters[1],
-                        Color::White,
-                        Color::Black,
-                    );
-                }
-            } else {
-                field.change_content(
-                    x as u16,
-                    y as u16,
-                    characters[0],
-                    Color::White,
-                    Color::Black,
-                );
+//What happened to the terminal since the last frame, the newest size if the window was resized
+struct TerminalEvents {
+    resized: Option<(u16, u16)>,
+    focus_lost: bool,
+}
+
+//Drains every pending event so nothing waits for the next frame,
+//returns the newest terminal size if the window was resized
+fn read_events(keyboard: &mut Keyboard) -> Result<TerminalEvents> {
+    let mut terminal_events = TerminalEvents {
+        resized: None,
+        focus_lost: false,
+    };
+    while poll(Duration::ZERO)? {
+        match read()? {
+            Event::Key(input_event) if terminal::is_interrupt(&input_event) => {
+                terminal::interrupt(130)
             }
+            Event::Key(input_event) => keyboard.handle(input_event),
+            Event::Resize(width, height) => terminal_events.resized = Some((width, height)),
+            Event::FocusLost => terminal_events.focus_lost = true,
+            _ => (),
         }
     }
-    field
+    Ok(terminal_events)
 }
 
-fn build_tetromino() -> Vec<String> {
-    //This could be more optimal left for readability
-    let mut tetromino = vec![];
-    let mut shape = String::from("");
-    shape.push_str("..X.");
-    shape.push_str("..X.");
-    shape.push_str("..X.");
-    shape.push_str("..X.");
-    tetromino.push(shape);
-    let mut shape = String::from("");
-    shape.push_str("..X.");
-    shape.push_str(".XX.");
-    shape.push_str(".X..");
-    shape.push_str("....");
-    tetromino.push(shape);
-    let mut shape = String::from("");
-    shape.push_str(".X..");
-    shape.push_str(".XX.");
-    shape.push_str("..X.");
-    shape.push_str("....");
-    tetromino.push(shape);
-    let mut shape = String::from("");
-    shape.push_str("....");
-    shape.push_str(".XX.");
-    shape.push_str("..X.");
-    shape.push_str("..X.");
-    tetromino.push(shape);
-    let mut shape = String::from("");
-    shape.push_str("....");
-    shape.push_str(".XX.");
-    shape.push_str(".X..");
-    shape.push_str(".X..");
-    tetromino.push(shape);
-    let mut shape = String::from("");
-    shape.push_str("....");
-    shape.push_str(".XX.");
-    shape.push_str(".XX.");
-    shape.push_str("....");
-    tetromino.push(shape);
-    let mut shape = String::from("");
-    shape.push_str("..X.");
-    shape.push_str(".XX.");
-    shape.push_str("..X.");
-    shape.push_str("....");
-    tetromino.push(shape);
-    tetromino
+fn set_input(input_state: &mut Input, keyboard: &Keyboard, controls: &Controls) {
+    let held = |action| keyboard.is_any_held(controls.keys(action));
+    let pressed = |action| keyboard.was_any_pressed(controls.keys(action));
+    input_state.left = held(Action::MoveLeft);
+    input_state.right = held(Action::MoveRight);
+    input_state.down = held(Action::SoftDrop);
+    input_state.rotate = pressed(Action::RotateClockwise);
+    input_state.rotate_counter_clockwise = pressed(Action::RotateCounterClockwise);
+    input_state.rotate_half_turn = pressed(Action::RotateHalfTurn);
+    input_state.hard_drop = pressed(Action::HardDrop);
+    input_state.sonic_drop = pressed(Action::SonicDrop);
+    input_state.hold = pressed(Action::Hold);
 }
@@ -0,0 +1,260 @@
+use crossterm::{event::KeyCode, style::Color};
+use std::io::Result;
+use tetris::engine::GameMode;
+use tetris::engine::GameSettings;
+use tetris::render::Frame;
+use tetris::render::FrameStyle;
+use tetris::render::Screen;
+use tetris::render::Text;
+
+use crate::config::Config;
+use crate::controls::Controls;
+use crate::controls_screen::controls_screen;
+use crate::game_view::format_time;
+use crate::high_scores::HighScores;
+use crate::menu::Menu;
+use crate::save_game;
+use crate::save_game::SavedGame;
+use crate::settings_screen::settings_screen;
+use crate::ui;
+use crate::ScreenSetting;
+
+//What the main menu was left for, a new game or the one that was saved on quit
+pub enum MenuChoice {
+    Play(GameMode),
+    Continue(Box<SavedGame>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Entry {
+    Continue,
+    Play,
+    Settings,
+    HighScores,
+    Controls,
+    Quit,
+}
+
+impl Entry {
+    fn label(&self) -> &'static str {
+        match self {
+            Entry::Continue => "Continue",
+            Entry::Play => "Play",
+            Entry::Settings => "Settings",
+            Entry::HighScores => "High Scores",
+            Entry::Controls => "Controls",
+            Entry::Quit => "Quit",
+        }
+    }
+}
+
+//Shown between games, returns what to play or None to quit.
+//Continue is only offered while there is a saved game.
+pub fn main_menu(
+    screen: &mut Screen,
+    screen_settings: &mut ScreenSetting,
+    game_settings: &mut GameSettings,
+    config: &mut Config,
+    controls: &mut Controls,
+    high_scores: &HighScores,
+) -> Result<Option<MenuChoice>> {
+    let title = Text::new(String::from(" TERMTRIS "), Color::Cyan, Color::Black);
+    let mut entries = vec![
+        Entry::Play,
+        Entry::Settings,
+        Entry::HighScores,
+        Entry::Controls,
+        Entry::Quit,
+    ];
+    if save_game::exists() {
+        entries.insert(0, Entry::Continue);
+    }
+    let mut menu = Menu::new(
+        entries
+            .iter()
+            .map(|entry| String::from(entry.label()))
+            .collect(),
+    );
+    let mut status: Option<String> = None;
+    loop {
+        let frame_height = entries.len() as u16 + 4;
+        let frame = Frame::new(
+            30,
+            frame_height,
+            FrameStyle::DoubleLine,
+            Color::White,
+            Color::Black,
+        );
+        ui::show(screen, screen_settings, |screen| {
+            screen.add_element_at(&frame, 2, 4);
+            screen.add_element_at(&title, 12, 4);
+            menu.render(screen, 6, 6);
+            if let Some(message) = &status {
+                let pos_y = 4 + frame_height;
+                let heading = String::from("Could not continue the game,");
+                screen.add_string_at(heading, Color::Red, Color::Black, 2, pos_y);
+                screen.add_string_at(message.clone(), Color::Red, Color::Black, 2, pos_y + 1);
+            }
+        })?;
+        let event = match ui::next_key_event(screen)? {
+            Some(event) => event,
+            None => continue,
+        };
+        match event.code {
+            KeyCode::Esc => return Ok(None),
+            _ if menu.handle(&event, controls) => match entries[menu.selected()] {
+                Entry::Continue => match save_game::load() {
+                    Some(Ok(saved_game)) => {
+                        return Ok(Some(MenuChoice::Continue(Box::new(saved_game))));
+                    }
+                    //The file was moved aside so the entry goes away with it
+                    failed => {
+                        status = failed.and_then(|result| result.err());
+                        entries.remove(0);
+                        menu = Menu::new(
+                            entries
+                                .iter()
+                                .map(|entry| String::from(entry.label()))
+                                .collect(),
+                        );
+                    }
+                },
+                Entry::Play => {
+                    let last_mode = game_settings.mode;
+                    if let Some(mode) = choose_mode(screen, screen_settings, controls, last_mode)? {
+                        return Ok(Some(MenuChoice::Play(mode)));
+                    }
+                }
+                Entry::Settings => {
+                    settings_screen(screen, screen_settings, game_settings, config, controls)?
+                }
+                Entry::HighScores => {
+                    let mode = game_settings.mode;
+                    high_scores_screen(screen, screen_settings, controls, high_scores, mode)?
+                }
+                Entry::Controls => controls_screen(screen, screen_settings, controls, config)?,
+                Entry::Quit => return Ok(None),
+            },
+            _ => (),
+        }
+    }
+}
+
+//None goes back to the main menu
+fn choose_mode(
+    screen: &mut Screen,
+    screen_settings: &ScreenSetting,
+    controls: &Controls,
+    last_mode: GameMode,
+) -> Result<Option<GameMode>> {
+    let frame = Frame::new(30, 9, FrameStyle::DoubleLine, Color::White, Color::Black);
+    let title = Text::new(String::from(" PLAY "), Color::Cyan, Color::Black);
+    let names = GameMode::ALL
+        .iter()
+        .map(|mode| {
+            let name = mode.name();
+            name[..1].to_uppercase() + &name[1..]
+        })
+        .collect();
+    let mut menu = Menu::new(names);
+    let last_index = GameMode::ALL.iter().position(|mode| *mode == last_mode);
+    menu.select(last_index.unwrap_or(0));
+    loop {
+        let mode = GameMode::ALL[menu.selected()];
+        ui::show(screen, screen_settings, |screen| {
+            screen.add_element_at(&frame, 2, 4);
+            screen.add_element_at(&title, 14, 4);
+            menu.render(screen, 6, 6);
+            let description = String::from(mode.description());
+            screen.add_string_at(description, Color::DarkGrey, Color::Black, 4, 10);
+        })?;
+        let event = match ui::next_key_event(screen)? {
+            Some(event) => event,
+            None => continue,
+        };
+        match event.code {
+            KeyCode::Esc => return Ok(None),
+            _ if menu.handle(&event, controls) => return Ok(Some(mode)),
+            _ => (),
+        }
+    }
+}
+
+//Left and right switch between the tables of the modes, up and down pick an entry to show in full
+pub fn high_scores_screen(
+    screen: &mut Screen,
+    screen_settings: &ScreenSetting,
+    controls: &Controls,
+    high_scores: &HighScores,
+    mode: GameMode,
+) -> Result<()> {
+    let frame = Frame::new(32, 18, FrameStyle::DoubleLine, Color::White, Color::Black);
+    let title = Text::new(String::from(" HIGH SCORES "), Color::Cyan, Color::Black);
+    let mut mode_index = GameMode::ALL
+        .iter()
+        .position(|other| *other == mode)
+        .unwrap_or(0);
+    let mut selected = 0;
+    loop {
+        let mode = GameMode::ALL[mode_index];
+        let entries = high_scores.entries(mode);
+        ui::show(screen, screen_settings, |screen| {
+            screen.add_element_at(&frame, 1, 1);
+            screen.add_element_at(&title, 10, 1);
+            let heading = format!("< {:^24} >", mode.name().to_uppercase());
+            screen.add_string_at(heading, Color::Yellow, Color::Black, 3, 2);
+            if entries.is_empty() {
+                let message = String::from("No scores yet");
+                screen.add_string_at(message, Color::White, Color::Black, 3, 4);
+            }
+            for (index, entry) in entries.iter().enumerate() {
+                //Sprint is ranked by time so that is what the table shows
+                let result = if mode.line_goal().is_some() {
+                    format_time(entry.frames)
+                } else {
+                    entry.score.to_string()
+                };
+                let line = format!(
+                    "{:>2} {:<3} {:>9}  {}",
+                    index + 1,
+                    entry.name,
+                    result,
+                    entry.date
+                );
+                let color = if index == selected {
+                    Color::Yellow
+                } else {
+                    Color::White
+                };
+                screen.add_string_at(line, color, Color::Black, 3, 4 + index as u16);
+            }
+            if let Some(entry) = entries.get(selected) {
+                let details = format!(
+                    "Lines {} Level {} Time {}",
+                    entry.lines,
+                    entry.level,
+                    format_time(entry.frames)
+                );
+                screen.add_string_at(details, Color::Cyan, Color::Black, 3, 15);
+                let seed = format!("Seed {}", entry.seed);
+                screen.add_string_at(seed, Color::Cyan, Color::Black, 3, 16);
+            }
+            let prompt = String::from("Esc to go back");
+            screen.add_string_at(prompt, Color::DarkGrey, Color::Black, 3, 17);
+        })?;
+        match ui::next_key_event(screen)?.map(|event| controls.menu_key(&event)) {
+            Some(KeyCode::Left) => {
+                mode_index = (mode_index + GameMode::ALL.len() - 1) % GameMode::ALL.len();
+                selected = 0;
+            }
+            Some(KeyCode::Right) => {
+                mode_index = (mode_index + 1) % GameMode::ALL.len();
+                selected = 0;
+            }
+            Some(KeyCode::Up) => selected = selected.saturating_sub(1),
+            Some(KeyCode::Down) => selected = (selected + 1).min(entries.len().saturating_sub(1)),
+            Some(KeyCode::Esc) | Some(KeyCode::Enter) => return Ok(()),
+            _ => (),
+        }
+    }
+}
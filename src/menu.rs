@@ -0,0 +1,61 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::style::Color;
+use tetris::render::Screen;
+
+use crate::controls::Controls;
+
+//A vertical list of entries, the selected one is marked and highlighted
+pub struct Menu {
+    entries: Vec<String>,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new(entries: Vec<String>) -> Menu {
+        Menu {
+            entries,
+            selected: 0,
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len() - 1);
+    }
+
+    //Entries that show a value are rewritten whenever it changes
+    pub fn set_entry(&mut self, index: usize, entry: String) {
+        self.entries[index] = entry;
+    }
+
+    //Moves the selection with the arrow keys or the bound drop and rotate keys,
+    //returns true when the selected entry is chosen
+    pub fn handle(&mut self, event: &KeyEvent, controls: &Controls) -> bool {
+        match controls.menu_key(event) {
+            KeyCode::Up => {
+                self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+                false
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                self.selected = (self.selected + 1) % self.entries.len();
+                false
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => true,
+            _ => false,
+        }
+    }
+
+    pub fn render(&self, screen: &mut Screen, pos_x: u16, pos_y: u16) {
+        for (index, entry) in self.entries.iter().enumerate() {
+            let (line, color) = if index == self.selected {
+                (format!("> {}", entry), Color::Yellow)
+            } else {
+                (format!("  {}", entry), Color::White)
+            };
+            screen.add_string_at(line, color, Color::Black, pos_x, pos_y + index as u16);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use crossterm::event::{poll, KeyCode, KeyModifiers};
+use std::io::Result;
+use std::time::{Duration, Instant};
+use tetris::engine::Game;
+use tetris::engine::GameEvent;
+use tetris::engine::GameSettings;
+use tetris::engine::FRAMES_PER_SECOND;
+use tetris::render::Screen;
+
+use crate::game_view::GameView;
+use crate::keyboard::Keyboard;
+use crate::keys::KeyBinding;
+use crate::replay::Replay;
+use crate::ui;
+use crate::{read_events, ScreenSetting, MAX_CATCH_UP_FRAMES};
+
+const SPEEDS: [u32; 3] = [1, 2, 4];
+
+//Space or p pauses, + and - change the speed, . steps one frame while paused and
+//Esc or q stops. The last frame stays up until the replay is stopped.
+pub fn play_replay(
+    tetromino: Vec<String>,
+    screen: &mut Screen,
+    screen_settings: &ScreenSetting,
+    game_settings: &GameSettings,
+    keyboard: &mut Keyboard,
+    replay: &Replay,
+) -> Result<()> {
+    let mut game = Game::new(tetromino, game_settings);
+    let view = GameView::new(&game);
+    let mut inputs = replay.inputs();
+    let mut speed_index = 0;
+    let mut paused = false;
+    let mut ended = false;
+    let mut fits = ui::center_layout(screen, screen_settings);
+    let announcement_frames = 2 * FRAMES_PER_SECOND;
+    let mut announcement: Vec<String> = Vec::new();
+    let mut announcement_timer = 0;
+    let mut last_time = Instant::now();
+    let mut lag = Duration::ZERO;
+    let mut redraw = true;
+    loop {
+        let terminal_events = read_events(keyboard)?;
+        if let Some((width, height)) = terminal_events.resized {
+            screen.resize(width, height);
+            fits = ui::center_layout(screen, screen_settings);
+            redraw = true;
+        }
+        let pressed = |character| keyboard.was_pressed(key(KeyCode::Char(character)));
+        if keyboard.was_pressed(key(KeyCode::Esc)) || pressed('q') {
+            return Ok(());
+        }
+        let mut steps = 0;
+        if pressed(' ') || pressed('p') {
+            paused = !paused;
+            redraw = true;
+        }
+        if pressed('+') || pressed('=') {
+            speed_index = (speed_index + 1).min(SPEEDS.len() - 1);
+            redraw = true;
+        }
+        if pressed('-') {
+            speed_index = speed_index.saturating_sub(1);
+            redraw = true;
+        }
+        if pressed('.') && paused {
+            steps = 1;
+        }
+        keyboard.clear_presses();
+        let frame_duration = Duration::from_secs(1) / (FRAMES_PER_SECOND * SPEEDS[speed_index]);
+        let now = Instant::now();
+        lag = (lag + (now - last_time)).min(frame_duration * MAX_CATCH_UP_FRAMES);
+        last_time = now;
+        if paused || ended || !fits {
+            lag = Duration::ZERO;
+        }
+        while lag >= frame_duration {
+            lag -= frame_duration;
+            steps += 1;
+        }
+        for _ in 0..steps {
+            let input = match inputs.next() {
+                Some(input) => input,
+                None => {
+                    ended = true;
+                    redraw = true;
+                    break;
+                }
+            };
+            for event in game.step(&input) {
+                if let GameEvent::Scored(score_event) = event {
+                    announcement = score_event.labels();
+                    announcement_timer = announcement_frames;
+                }
+            }
+            announcement_timer = announcement_timer.saturating_sub(1);
+            redraw = true;
+        }
+        if !redraw {
+            poll(frame_duration - lag)?;
+            continue;
+        }
+        redraw = false;
+        if !fits {
+            ui::render_too_small(screen, screen_settings)?;
+            continue;
+        }
+        if announcement_timer == 0 {
+            announcement.clear();
+        }
+        let status = if ended {
+            String::from("END")
+        } else if paused {
+            String::from("PAUSED")
+        } else {
+            format!("REPLAY {}x", SPEEDS[speed_index])
+        };
+        let mut lines = vec![status];
+        lines.extend(announcement.iter().cloned());
+        screen.begin_render();
+        view.render(screen, &game, screen_settings.show_ghost, &lines);
+        screen.end_render()?;
+    }
+}
+
+fn key(code: KeyCode) -> KeyBinding {
+    KeyBinding::new(code, KeyModifiers::NONE)
+}
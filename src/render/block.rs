@@ -1,6 +1,7 @@
 use super::screen::ScreenElement;
 use crossterm::style::Color;
 
+#[derive(Clone, PartialEq, Debug)]
 pub struct Block {
     content: Vec<char>,
     foreground_colors: Vec<Color>,
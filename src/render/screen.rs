@@ -2,9 +2,16 @@ use crossterm::{
     cursor::MoveTo,
     queue,
     style::{Color, Print, SetBackgroundColor, SetForegroundColor},
-    Result,
 };
-use std::io::{stdout, Write};
+use std::io::{stdout, Result, Write};
+
+//What the terminal shows for one cell
+#[derive(Clone, Copy, PartialEq)]
+struct Cell {
+    content: char,
+    foreground_color: Color,
+    background_color: Color,
+}
 
 pub trait ScreenElement {
     fn get_width(&self) -> u16;
@@ -20,21 +27,80 @@ pub struct Screen {
     background_colors: Vec<Color>,
     width: u16,
     height: u16,
+    //Added to every position so a layout can be moved around as a whole
+    origin_x: u16,
+    origin_y: u16,
+    //The last frame sent to the terminal, None until the first one
+    shown: Option<Vec<Cell>>,
+    bytes_written: usize,
+    //For terminals without line drawing characters or colors
+    ascii: bool,
+    colors: bool,
 }
 
 impl Screen {
     pub fn new(width: u16, height: u16) -> Screen {
+        //Large terminals have more cells than fit in a u16
+        let size = width as usize * height as usize;
         Screen {
             width,
             height,
-            buffer: vec![' '; (width * height) as usize],
-            foreground_colors: vec![Color::Black; (width * height) as usize],
-            background_colors: vec![Color::Black; (width * height) as usize],
+            buffer: vec![' '; size],
+            foreground_colors: vec![Color::Black; size],
+            background_colors: vec![Color::Black; size],
+            origin_x: 0,
+            origin_y: 0,
+            shown: None,
+            bytes_written: 0,
+            ascii: false,
+            colors: true,
+        }
+    }
+
+    pub fn set_ascii(&mut self, ascii: bool) {
+        self.ascii = ascii;
+        self.shown = None;
+    }
+
+    pub fn set_colors(&mut self, colors: bool) {
+        self.colors = colors;
+        self.shown = None;
+    }
+
+    pub fn get_width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u16 {
+        self.height
+    }
+
+    //Follows the terminal size, the next frame is sent in full
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+        self.shown = None;
+        self.begin_render();
+    }
+
+    pub fn set_origin(&mut self, origin_x: u16, origin_y: u16) {
+        self.origin_x = origin_x;
+        self.origin_y = origin_y;
+    }
+
+    //Cells outside of the screen are dropped instead of wrapping or panicking
+    fn index_of(&self, pos_x: u16, pos_y: u16) -> Option<usize> {
+        let x = pos_x as usize + self.origin_x as usize;
+        let y = pos_y as usize + self.origin_y as usize;
+        if x < self.width as usize && y < self.height as usize {
+            Some(y * self.width as usize + x)
+        } else {
+            None
         }
     }
 
     pub fn begin_render(&mut self) {
-        let size = (self.width * self.height) as usize;
+        let size = self.width as usize * self.height as usize;
         self.buffer = vec![' '; size];
         self.foreground_colors = vec![Color::Black; size];
         self.background_colors = vec![Color::Black; size];
@@ -43,7 +109,9 @@ impl Screen {
     pub fn add_element_at<T: ScreenElement>(&mut self, elem: &T, pos_x: u16, pos_y: u16) {
         for x in 0..elem.get_width() {
             for y in 0..elem.get_height() {
-                let index = ((y + pos_y) * self.width + (x + pos_x)) as usize;
+                let Some(index) = self.index_of(x + pos_x, y + pos_y) else {
+                    continue;
+                };
                 self.buffer[index] = elem.get_part(x, y);
                 self.foreground_colors[index] = elem.get_foreground_color(x, y);
                 self.background_colors[index] = elem.get_background_color(x, y);
@@ -59,7 +127,9 @@ impl Screen {
         pos_x: u16,
         pos_y: u16,
     ) {
-        let index = ((pos_y) * self.width + (pos_x)) as usize;
+        let Some(index) = self.index_of(pos_x, pos_y) else {
+            return;
+        };
         self.buffer[index] = content;
         self.foreground_colors[index] = foreground_color;
         self.background_colors[index] = background_color;
@@ -75,31 +145,215 @@ impl Screen {
     ) {
         let mut iter = 0;
         text.chars().for_each(|this_char| {
-            let index = ((pos_y) * self.width + (pos_x + iter)) as usize;
-            self.buffer[index] = this_char;
-            self.foreground_colors[index] = foreground_color;
-            self.background_colors[index] = background_color;
+            if let Some(index) = self.index_of(pos_x + iter, pos_y) {
+                self.buffer[index] = this_char;
+                self.foreground_colors[index] = foreground_color;
+                self.background_colors[index] = background_color;
+            }
             iter += 1;
         });
     }
 
-    pub fn end_render(&self) -> Result<()> {
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let index = (y * self.width + x) as usize;
-                let current_char = self.buffer[index];
-                let foreground_color = self.foreground_colors[index];
-                let background_color = self.background_colors[index];
-                queue!(
-                    stdout(),
-                    MoveTo(x, y),
-                    SetBackgroundColor(background_color),
-                    SetForegroundColor(foreground_color),
-                    Print(current_char)
-                )?;
+    pub fn end_render(&mut self) -> Result<()> {
+        self.end_render_to(&mut stdout())
+    }
+
+    //Only sends the cells that changed since the last frame, a run of them
+    //shares one cursor move and colors are only set when they differ.
+    //The frame only counts as shown once it was written, after a failed write
+    //the next frame is sent in full.
+    pub fn end_render_to<W: Write>(&mut self, out: &mut W) -> Result<()> {
+        let mut output: Vec<u8> = Vec::new();
+        let shown = self.shown.take();
+        let mut cursor: Option<(u16, u16)> = None;
+        //Each color is only sent when it differs from the one the terminal is using
+        let mut foreground: Option<Color> = None;
+        let mut background: Option<Color> = None;
+        let cells = self.cells();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y as usize * self.width as usize + x as usize;
+                let cell = cells[index];
+                if let Some(shown) = &shown {
+                    if shown[index] == cell {
+                        continue;
+                    }
+                }
+                if cursor != Some((x, y)) {
+                    queue!(output, MoveTo(x, y))?;
+                }
+                if self.colors && background != Some(cell.background_color) {
+                    queue!(output, SetBackgroundColor(cell.background_color))?;
+                    background = Some(cell.background_color);
+                }
+                if self.colors && foreground != Some(cell.foreground_color) {
+                    queue!(output, SetForegroundColor(cell.foreground_color))?;
+                    foreground = Some(cell.foreground_color);
+                }
+                let content = if self.ascii {
+                    to_ascii(cell.content)
+                } else {
+                    cell.content
+                };
+                queue!(output, Print(content))?;
+                cursor = Some((x + 1, y));
             }
         }
-        stdout().flush()?;
+        out.write_all(&output)?;
+        out.flush()?;
+        self.shown = Some(cells);
+        self.bytes_written = output.len();
         Ok(())
     }
+
+    //Bytes sent to the terminal by the last end_render
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        (0..self.buffer.len())
+            .map(|index| Cell {
+                content: self.buffer[index],
+                foreground_color: self.foreground_colors[index],
+                background_color: self.background_colors[index],
+            })
+            .collect()
+    }
+}
+
+//The closest plain character to the ones the frames, the field and the ghost use
+fn to_ascii(content: char) -> char {
+    match content {
+        '║' | '│' => '|',
+        '═' | '─' => '-',
+        '╔' | '╗' | '╚' | '╝' | '┌' | '┐' | '└' | '┘' => '+',
+        '░' => '.',
+        content if content.is_ascii() => content,
+        _ => '#',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    //Draws the same text every frame, with one cell changed when asked
+    fn render(screen: &mut Screen, changed: Option<char>) -> Vec<u8> {
+        screen.begin_render();
+        screen.add_string_at(String::from("abcd"), Color::White, Color::Black, 0, 0);
+        screen.add_string_at(String::from("efgh"), Color::White, Color::Black, 0, 1);
+        if let Some(content) = changed {
+            screen.add_directly(content, Color::White, Color::Black, 2, 1);
+        }
+        let mut output = Vec::new();
+        screen.end_render_to(&mut output).unwrap();
+        output
+    }
+
+    fn expected(commands: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> Vec<u8> {
+        let mut output = Vec::new();
+        commands(&mut output).unwrap();
+        output
+    }
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> Result<usize> {
+            Err(ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn the_first_frame_is_sent_in_one_run() {
+        let mut screen = Screen::new(4, 2);
+        let output = render(&mut screen, None);
+        let first_line = expected(|output| {
+            queue!(
+                output,
+                MoveTo(0, 0),
+                SetBackgroundColor(Color::Black),
+                SetForegroundColor(Color::White),
+                Print('a'),
+                Print('b'),
+                Print('c'),
+                Print('d'),
+                MoveTo(0, 1),
+            )
+        });
+        assert!(output.starts_with(&first_line));
+        assert_eq!(screen.bytes_written(), output.len());
+    }
+
+    #[test]
+    fn an_unchanged_frame_sends_nothing() {
+        let mut screen = Screen::new(4, 2);
+        render(&mut screen, None);
+        assert!(render(&mut screen, None).is_empty());
+        assert_eq!(screen.bytes_written(), 0);
+    }
+
+    #[test]
+    fn a_changed_cell_sends_one_cursor_move_and_the_cell() {
+        let mut screen = Screen::new(4, 2);
+        render(&mut screen, None);
+        let output = render(&mut screen, Some('x'));
+        let cell = expected(|output| {
+            queue!(
+                output,
+                MoveTo(2, 1),
+                SetBackgroundColor(Color::Black),
+                SetForegroundColor(Color::White),
+                Print('x'),
+            )
+        });
+        assert_eq!(output, cell);
+    }
+
+    #[test]
+    fn neighbouring_changes_share_the_cursor_move() {
+        let mut screen = Screen::new(4, 2);
+        render(&mut screen, None);
+        screen.begin_render();
+        screen.add_string_at(String::from("abyz"), Color::White, Color::Black, 0, 0);
+        screen.add_string_at(String::from("efgh"), Color::Red, Color::Black, 0, 1);
+        let mut output = Vec::new();
+        screen.end_render_to(&mut output).unwrap();
+        let run = expected(|output| {
+            queue!(
+                output,
+                MoveTo(2, 0),
+                SetBackgroundColor(Color::Black),
+                SetForegroundColor(Color::White),
+                Print('y'),
+                Print('z'),
+                MoveTo(0, 1),
+                SetForegroundColor(Color::Red),
+                Print('e'),
+                Print('f'),
+                Print('g'),
+                Print('h'),
+            )
+        });
+        assert_eq!(output, run);
+    }
+
+    #[test]
+    fn a_frame_that_failed_to_write_is_sent_again() {
+        let mut screen = Screen::new(4, 2);
+        render(&mut screen, None);
+        screen.begin_render();
+        screen.add_string_at(String::from("abcd"), Color::White, Color::Black, 0, 0);
+        screen.add_string_at(String::from("efxh"), Color::White, Color::Black, 0, 1);
+        assert!(screen.end_render_to(&mut FailingWriter).is_err());
+        let output = render(&mut screen, Some('x'));
+        assert!(!output.is_empty());
+        assert_eq!(output, render(&mut Screen::new(4, 2), Some('x')));
+    }
 }
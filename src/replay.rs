@@ -0,0 +1,378 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tetris::engine::GameMode;
+use tetris::engine::GameSettings;
+use tetris::engine::Input;
+use tetris::engine::RandomizerKind;
+use tetris::engine::MAX_PREVIEW;
+
+use crate::config::{COLUMNS, ROWS, START_LEVEL, WALLS_X, WALLS_Y};
+use crate::config::{LINE_CLEAR_DELAY_MS, LOCK_DELAY_MS, SHIFT_MS, SOFT_DROP_MS};
+use crate::high_scores;
+use crate::storage::write_atomically;
+
+pub const REPLAY_VERSION: u32 = 1;
+//The oldest replays are deleted once there are more
+const MAX_REPLAYS: usize = 100;
+
+//The input changed to these bits on this frame
+#[derive(Clone, Copy, PartialEq)]
+struct InputEvent {
+    frame: u64,
+    bits: u16,
+}
+
+//The engine is deterministic, so the seed, the settings and the input of every frame
+//are enough to play a game again
+pub struct Replay {
+    pub date: String,
+    pub seed: u64,
+    pub mode: GameMode,
+    pub field_width: i16,
+    pub field_height: i16,
+    pub preview_count: usize,
+    pub randomizer: RandomizerKind,
+    pub start_level: u32,
+    pub lock_delay_ms: u32,
+    pub line_clear_delay_ms: u32,
+    pub das_ms: u32,
+    pub arr_ms: u32,
+    pub soft_drop_ms: u32,
+    frames: u64,
+    events: Vec<InputEvent>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplayFile {
+    version: u32,
+    game_version: String,
+    date: String,
+    #[serde(with = "crate::storage::seed")]
+    seed: u64,
+    mode: String,
+    frames: u64,
+    //"frames:bits" pairs, the frames counted from the previous pair and the bits in hex
+    events: String,
+    settings: ReplaySettings,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReplaySettings {
+    columns: i16,
+    rows: i16,
+    preview: usize,
+    randomizer: String,
+    start_level: u32,
+    lock_delay_ms: u32,
+    line_clear_delay_ms: u32,
+    das_ms: u32,
+    arr_ms: u32,
+    soft_drop_ms: u32,
+}
+
+impl Replay {
+    pub fn new(game_settings: &GameSettings) -> Replay {
+        Replay {
+            date: high_scores::today(),
+            seed: game_settings.seed,
+            mode: game_settings.mode,
+            field_width: game_settings.field_width,
+            field_height: game_settings.field_height,
+            preview_count: game_settings.preview_count,
+            randomizer: game_settings.randomizer,
+            start_level: game_settings.start_level,
+            lock_delay_ms: game_settings.lock_delay_ms,
+            line_clear_delay_ms: game_settings.line_clear_delay_ms,
+            das_ms: game_settings.das_ms,
+            arr_ms: game_settings.arr_ms,
+            soft_drop_ms: game_settings.soft_drop_ms,
+            frames: 0,
+            events: Vec::new(),
+        }
+    }
+
+    //Called with the input of every step, only changes are kept
+    pub fn record(&mut self, input: &Input) {
+        let bits = input.to_bits();
+        let last_bits = self.events.last().map(|event| event.bits).unwrap_or(0);
+        if bits != last_bits {
+            self.events.push(InputEvent {
+                frame: self.frames,
+                bits,
+            });
+        }
+        self.frames += 1;
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    //The input of every recorded frame in order
+    pub fn inputs(&self) -> impl Iterator<Item = Input> + '_ {
+        let mut next = 0;
+        let mut bits = 0;
+        (0..self.frames).map(move |frame| {
+            while next < self.events.len() && self.events[next].frame == frame {
+                bits = self.events[next].bits;
+                next += 1;
+            }
+            Input::from_bits(bits)
+        })
+    }
+
+    //The piece colors are not part of the game so they are left to the caller
+    pub fn apply(&self, game_settings: &mut GameSettings) {
+        game_settings.seed = self.seed;
+        game_settings.mode = self.mode;
+        game_settings.field_width = self.field_width;
+        game_settings.field_height = self.field_height;
+        game_settings.preview_count = self.preview_count;
+        game_settings.randomizer = self.randomizer;
+        game_settings.start_level = self.start_level;
+        game_settings.lock_delay_ms = self.lock_delay_ms;
+        game_settings.line_clear_delay_ms = self.line_clear_delay_ms;
+        game_settings.das_ms = self.das_ms;
+        game_settings.arr_ms = self.arr_ms;
+        game_settings.soft_drop_ms = self.soft_drop_ms;
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let text =
+            fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        let file: ReplayFile =
+            toml::from_str(&text).map_err(|error| format!("{}: {}", path.display(), error))?;
+        Replay::from_file(file).map_err(|message| format!("{}: {}", path.display(), message))
+    }
+
+    //Written to the replays directory next to the high scores, returns where it went
+    pub fn save(&self) -> Result<PathBuf, String> {
+        let directory = replays_path().ok_or_else(|| String::from("no data directory"))?;
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let path = directory.join(format!("{}-{}.replay", self.mode.name(), seconds));
+        let text = toml::to_string(&self.to_file()).map_err(|error| error.to_string())?;
+        write_atomically(&path, &text).map_err(|error| format!("{}: {}", path.display(), error))?;
+        prune(&directory);
+        Ok(path)
+    }
+
+    pub fn to_file(&self) -> ReplayFile {
+        let mut last_frame = 0;
+        let events: Vec<String> = self
+            .events
+            .iter()
+            .map(|event| {
+                let delta = event.frame - last_frame;
+                last_frame = event.frame;
+                format!("{}:{:x}", delta, event.bits)
+            })
+            .collect();
+        ReplayFile {
+            version: REPLAY_VERSION,
+            game_version: String::from(env!("CARGO_PKG_VERSION")),
+            date: self.date.clone(),
+            seed: self.seed,
+            mode: String::from(self.mode.name()),
+            frames: self.frames,
+            events: events.join(" "),
+            settings: ReplaySettings {
+                columns: self.field_width - WALLS_X,
+                rows: self.field_height - WALLS_Y,
+                preview: self.preview_count,
+                randomizer: String::from(self.randomizer.name()),
+                start_level: self.start_level,
+                lock_delay_ms: self.lock_delay_ms,
+                line_clear_delay_ms: self.line_clear_delay_ms,
+                das_ms: self.das_ms,
+                arr_ms: self.arr_ms,
+                soft_drop_ms: self.soft_drop_ms,
+            },
+        }
+    }
+
+    //A replay from a newer version might rely on rules this one does not know
+    pub fn from_file(file: ReplayFile) -> Result<Replay, String> {
+        if file.version != REPLAY_VERSION {
+            return Err(format!(
+                "replay version {} is not supported, expected {}",
+                file.version, REPLAY_VERSION
+            ));
+        }
+        let settings = &file.settings;
+        let mode = GameMode::from_name(&file.mode)
+            .ok_or_else(|| format!("unknown mode '{}'", file.mode))?;
+        let randomizer = RandomizerKind::from_name(&settings.randomizer)
+            .ok_or_else(|| format!("unknown randomizer '{}'", settings.randomizer))?;
+        let in_range = COLUMNS.contains(&(settings.columns as i64))
+            && ROWS.contains(&(settings.rows as i64))
+            && (1..=MAX_PREVIEW).contains(&settings.preview)
+            && START_LEVEL.contains(&(settings.start_level as i64))
+            && LOCK_DELAY_MS.contains(&(settings.lock_delay_ms as i64))
+            && LINE_CLEAR_DELAY_MS.contains(&(settings.line_clear_delay_ms as i64))
+            && SHIFT_MS.contains(&(settings.das_ms as i64))
+            && SHIFT_MS.contains(&(settings.arr_ms as i64))
+            && SOFT_DROP_MS.contains(&(settings.soft_drop_ms as i64));
+        if !in_range {
+            return Err(String::from("settings are out of range"));
+        }
+        let mut events = Vec::new();
+        let mut frame: u64 = 0;
+        for entry in file.events.split_whitespace() {
+            let event = entry.split_once(':').and_then(|(delta, bits)| {
+                Some((
+                    delta.parse::<u64>().ok()?,
+                    u16::from_str_radix(bits, 16).ok()?,
+                ))
+            });
+            let (delta, bits) = event.ok_or_else(|| format!("bad event '{}'", entry))?;
+            frame = frame
+                .checked_add(delta)
+                .filter(|frame| *frame < file.frames)
+                .ok_or_else(|| format!("event '{}' is past the end", entry))?;
+            events.push(InputEvent { frame, bits });
+        }
+        Ok(Replay {
+            date: file.date,
+            seed: file.seed,
+            mode,
+            field_width: settings.columns + WALLS_X,
+            field_height: settings.rows + WALLS_Y,
+            preview_count: settings.preview,
+            randomizer,
+            start_level: settings.start_level,
+            lock_delay_ms: settings.lock_delay_ms,
+            line_clear_delay_ms: settings.line_clear_delay_ms,
+            das_ms: settings.das_ms,
+            arr_ms: settings.arr_ms,
+            soft_drop_ms: settings.soft_drop_ms,
+            frames: file.frames,
+            events,
+        })
+    }
+}
+
+fn replays_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|directory| directory.join("termtris").join("replays"))
+}
+
+//Deleting is best effort, a replay that stays behind does no harm
+fn prune(directory: &Path) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut replays: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "replay")
+        })
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect();
+    if replays.len() <= MAX_REPLAYS {
+        return;
+    }
+    replays.sort();
+    for (_, path) in replays.iter().take(replays.len() - MAX_REPLAYS) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tetris::engine::{build_tetromino, Game};
+
+    fn game_settings() -> GameSettings {
+        let mut game_settings = GameSettings::new(12, 21);
+        game_settings.seed = u64::MAX - 3;
+        game_settings.mode = GameMode::Sprint;
+        game_settings.randomizer = RandomizerKind::Tgm;
+        game_settings.das_ms = 120;
+        game_settings
+    }
+
+    //Some frames change the input, most keep it
+    fn input_at(frame: u64) -> Input {
+        Input {
+            left: frame % 50 < 20,
+            down: frame % 70 > 60,
+            rotate: frame.is_multiple_of(33),
+            hard_drop: frame % 40 == 39,
+            ..Input::new()
+        }
+    }
+
+    fn round_trip(replay: &Replay) -> Result<Replay, String> {
+        let text = toml::to_string(&replay.to_file()).map_err(|error| error.to_string())?;
+        let file: ReplayFile = toml::from_str(&text).map_err(|error| error.to_string())?;
+        Replay::from_file(file)
+    }
+
+    #[test]
+    fn a_saved_replay_loads_with_the_same_settings_and_input() {
+        let mut replay = Replay::new(&game_settings());
+        for frame in 0..500 {
+            replay.record(&input_at(frame));
+        }
+        let loaded = round_trip(&replay).unwrap();
+        assert_eq!(loaded.frames(), 500);
+        let bits: Vec<u16> = loaded.inputs().map(|input| input.to_bits()).collect();
+        let expected: Vec<u16> = (0..500).map(|frame| input_at(frame).to_bits()).collect();
+        assert_eq!(bits, expected);
+        let mut settings = GameSettings::new(10, 10);
+        loaded.apply(&mut settings);
+        let original = game_settings();
+        assert_eq!(settings.seed, original.seed);
+        assert_eq!(settings.mode, original.mode);
+        assert_eq!(settings.randomizer, original.randomizer);
+        assert_eq!(
+            (settings.field_width, settings.field_height),
+            (original.field_width, original.field_height)
+        );
+        assert_eq!(settings.das_ms, original.das_ms);
+    }
+
+    #[test]
+    fn playing_a_loaded_replay_ends_in_the_same_game() {
+        let settings = game_settings();
+        let mut game = Game::new(build_tetromino(), &settings);
+        let mut replay = Replay::new(&settings);
+        for frame in 0..600 {
+            let input = input_at(frame);
+            replay.record(&input);
+            game.step(&input);
+        }
+        let loaded = round_trip(&replay).unwrap();
+        let mut replayed_settings = GameSettings::new(12, 21);
+        loaded.apply(&mut replayed_settings);
+        let mut replayed = Game::new(build_tetromino(), &replayed_settings);
+        for input in loaded.inputs() {
+            replayed.step(&input);
+        }
+        assert!(game.pieces_placed() > 0);
+        assert_eq!(replayed.snapshot(), game.snapshot());
+    }
+
+    #[test]
+    fn a_replay_with_bad_settings_or_events_is_refused() {
+        let replay = Replay::new(&game_settings());
+        let mut file = replay.to_file();
+        file.settings.das_ms = 4_000_000;
+        assert!(Replay::from_file(file).is_err());
+        let mut file = replay.to_file();
+        file.events = String::from("0:1 5:0");
+        assert!(Replay::from_file(file).is_err());
+        let mut file = replay.to_file();
+        file.version = REPLAY_VERSION + 1;
+        assert!(Replay::from_file(file).is_err());
+    }
+}
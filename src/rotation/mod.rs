@@ -1,2 +1,5 @@
 pub mod rotation_calculations;
+pub mod wall_kicks;
 pub use self::rotation_calculations::Rotation;
+pub use self::wall_kicks::Kick;
+pub use self::wall_kicks::KickTable;
@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Rotation {
     R0,
     R90,
@@ -14,7 +15,7 @@ impl Rotation {
             Rotation::R270 => Rotation::R0,
         }
     }
-    pub fn _rotate_counter_clockwise(p_rotation: &Rotation) -> Rotation {
+    pub fn rotate_counter_clockwise(p_rotation: &Rotation) -> Rotation {
         match p_rotation {
             Rotation::R0 => Rotation::R270,
             Rotation::R90 => Rotation::R0,
@@ -22,13 +23,23 @@ impl Rotation {
             Rotation::R270 => Rotation::R180,
         }
     }
+    pub fn rotate_half_turn(p_rotation: &Rotation) -> Rotation {
+        match p_rotation {
+            Rotation::R0 => Rotation::R180,
+            Rotation::R90 => Rotation::R270,
+            Rotation::R180 => Rotation::R0,
+            Rotation::R270 => Rotation::R90,
+        }
+    }
 
-    pub fn rotate(p_pos_x: i16, p_pos_y: i16, p_rotation: &Rotation) -> i16 {
+    //Maps a cell inside the rotated p_size box to its index in the 4x4 shape string
+    pub fn rotate(p_pos_x: i16, p_pos_y: i16, p_size: i16, p_rotation: &Rotation) -> i16 {
+        let last = p_size - 1;
         match p_rotation {
             Rotation::R0 => p_pos_y * 4 + p_pos_x,
-            Rotation::R90 => 12 + p_pos_y - (4 * p_pos_x),
-            Rotation::R180 => 15 - (p_pos_y * 4) - p_pos_x,
-            Rotation::R270 => 3 - p_pos_y + (4 * p_pos_x),
+            Rotation::R90 => (last - p_pos_x) * 4 + p_pos_y,
+            Rotation::R180 => (last - p_pos_y) * 4 + (last - p_pos_x),
+            Rotation::R270 => p_pos_x * 4 + (last - p_pos_y),
         }
     }
 }
@@ -0,0 +1,132 @@
+use super::rotation_calculations::Rotation;
+
+//SRS offset data, y points up as in the guideline. Every rotation state has five
+//offsets and the kicks between two states are the differences between them.
+const JLSTZ_OFFSETS: [[(i16, i16); 5]; 4] = [
+    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (0, 0), (0, 0), (0, 0), (0, 0)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+
+const I_OFFSETS: [[(i16, i16); 5]; 4] = [
+    [(0, 0), (-1, 0), (2, 0), (-1, 0), (2, 0)],
+    [(-1, 0), (0, 0), (0, 0), (0, 1), (0, -2)],
+    [(-1, 1), (1, 1), (-2, 1), (1, 0), (-2, 0)],
+    [(0, 1), (0, 1), (0, 1), (0, -1), (0, 2)],
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum KickTable {
+    Jlstz,
+    I,
+    O,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Kick {
+    pub test: usize,
+    pub offset_x: i16,
+    pub offset_y: i16,
+}
+
+impl KickTable {
+    //Returns the translations to try in order, in field coordinates (y down).
+    //The shapes rotate inside their bounding box, so the first offset pair is
+    //subtracted to make the first test always (0, 0).
+    pub fn kicks(&self, p_from: &Rotation, p_to: &Rotation) -> Vec<(i16, i16)> {
+        let offsets = match self {
+            KickTable::Jlstz => &JLSTZ_OFFSETS,
+            KickTable::I => &I_OFFSETS,
+            KickTable::O => return vec![(0, 0)],
+        };
+        let from = &offsets[state_index(p_from)];
+        let to = &offsets[state_index(p_to)];
+        let base_x = from[0].0 - to[0].0;
+        let base_y = from[0].1 - to[0].1;
+        from.iter()
+            .zip(to.iter())
+            .map(|(from, to)| {
+                let kick_x = from.0 - to.0 - base_x;
+                let kick_y = from.1 - to.1 - base_y;
+                (kick_x, -kick_y)
+            })
+            .collect()
+    }
+}
+
+fn state_index(p_rotation: &Rotation) -> usize {
+    match p_rotation {
+        Rotation::R0 => 0,
+        Rotation::R90 => 1,
+        Rotation::R180 => 2,
+        Rotation::R270 => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATES: [Rotation; 4] = [Rotation::R0, Rotation::R90, Rotation::R180, Rotation::R270];
+
+    //The guideline kick tables for each clockwise turn, y pointing up
+    const JLSTZ_CLOCKWISE: [[(i16, i16); 5]; 4] = [
+        [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+        [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+        [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+        [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    ];
+
+    const I_CLOCKWISE: [[(i16, i16); 5]; 4] = [
+        [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+        [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+        [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+        [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    ];
+
+    fn field_kicks(p_table: &[(i16, i16); 5]) -> Vec<(i16, i16)> {
+        p_table
+            .iter()
+            .map(|(kick_x, kick_y)| (*kick_x, -kick_y))
+            .collect()
+    }
+
+    fn reversed(p_kicks: &[(i16, i16)]) -> Vec<(i16, i16)> {
+        p_kicks
+            .iter()
+            .map(|(kick_x, kick_y)| (-kick_x, -kick_y))
+            .collect()
+    }
+
+    #[test]
+    fn clockwise_kicks_match_the_guideline() {
+        for (table, expected) in [
+            (KickTable::Jlstz, &JLSTZ_CLOCKWISE),
+            (KickTable::I, &I_CLOCKWISE),
+        ] {
+            for (index, from) in STATES.iter().enumerate() {
+                let to = Rotation::rotate_clockwise(from);
+                assert_eq!(table.kicks(from, &to), field_kicks(&expected[index]));
+            }
+        }
+    }
+
+    #[test]
+    fn counter_clockwise_kicks_undo_the_clockwise_ones() {
+        for table in [KickTable::Jlstz, KickTable::I] {
+            for from in STATES.iter() {
+                let to = Rotation::rotate_clockwise(from);
+                assert_eq!(table.kicks(&to, from), reversed(&table.kicks(from, &to)));
+            }
+        }
+    }
+
+    #[test]
+    fn the_o_piece_never_kicks() {
+        for from in STATES.iter() {
+            let to = Rotation::rotate_clockwise(from);
+            assert_eq!(KickTable::O.kicks(from, &to), vec![(0, 0)]);
+        }
+    }
+}
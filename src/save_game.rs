@@ -0,0 +1,549 @@
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use tetris::engine::build_tetromino;
+use tetris::engine::gravity::ROW;
+use tetris::engine::ms_to_frames;
+use tetris::engine::AutoShift;
+use tetris::engine::Game;
+use tetris::engine::GameMode;
+use tetris::engine::GameSnapshot;
+use tetris::engine::GameState;
+use tetris::engine::RandomizerKind;
+use tetris::engine::Scoring;
+use tetris::engine::TetrisShape;
+use tetris::engine::MAX_LOCK_RESETS;
+use tetris::render::screen::ScreenElement;
+use tetris::render::Block;
+use tetris::rotation::Kick;
+use tetris::rotation::Rotation;
+
+use crate::config::{color_name, parse_color};
+use crate::config::{COLUMNS, ROWS, START_LEVEL, WALLS_X, WALLS_Y};
+use crate::config::{LINE_CLEAR_DELAY_MS, LOCK_DELAY_MS, SHIFT_MS, SOFT_DROP_MS};
+use crate::high_scores;
+use crate::replay::{Replay, ReplayFile};
+use crate::storage::write_atomically;
+
+//Raised whenever the file changes shape, older saves are refused rather than guessed at
+pub const SAVE_VERSION: u32 = 1;
+//Field cells name their colors by position in the list, one digit each
+const COLOR_DIGITS: u32 = 36;
+
+//A game that was quit halfway, with the replay recorded so far
+pub struct SavedGame {
+    pub game: Game,
+    pub replay: Replay,
+}
+
+//Only the version is read first, so a file from another version is rejected by
+//what it says it is instead of by whichever field changed
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SaveFile {
+    version: u32,
+    game_version: String,
+    date: String,
+    mode: String,
+    #[serde(with = "crate::storage::seed")]
+    seed: u64,
+    //How many words the generator handed out, a 128 bit number so it is a string
+    rng_position: String,
+    randomizer: String,
+    randomizer_state: Vec<i16>,
+    score: u32,
+    start_level: u32,
+    level: u32,
+    lines_cleared: u32,
+    pieces_placed: u32,
+    frames_played: u64,
+    queue: Vec<i16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hold: Option<i16>,
+    can_hold: bool,
+    piece_colors: Vec<String>,
+    piece: SavedPiece,
+    field: SavedField,
+    timers: SavedTimers,
+    scoring: SavedScoring,
+    replay: ReplayFile,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SavedPiece {
+    kind: i16,
+    x: i16,
+    y: i16,
+    rotation: u16,
+    color: String,
+}
+
+//One string per row with the walls included, the colors as digits into `colors`
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SavedField {
+    colors: Vec<String>,
+    rows: Vec<String>,
+    foreground: Vec<String>,
+    background: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SavedTimers {
+    //Missing while a piece is falling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clearing_frames: Option<u32>,
+    clearing_rows: Vec<i16>,
+    line_clear_frames: u32,
+    gravity_progress: u64,
+    lock_delay_ms: u32,
+    lock_timer: u32,
+    lock_resets: u32,
+    lowest_y: i16,
+    das_frames: u32,
+    arr_frames: u32,
+    shift_direction: i16,
+    shift_timer: u32,
+    left_held: bool,
+    right_held: bool,
+    soft_drop_frames: u32,
+    soft_drop_timer: u32,
+    soft_drop_held: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SavedScoring {
+    back_to_back: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    combo: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_kick: Option<SavedKick>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SavedKick {
+    test: usize,
+    offset_x: i16,
+    offset_y: i16,
+}
+
+pub fn exists() -> bool {
+    save_path().is_some_and(|path| path.exists())
+}
+
+//There is one slot, a later save replaces the earlier one
+pub fn save(game: &Game, replay: &Replay) -> Result<(), String> {
+    let path = save_path().ok_or_else(|| String::from("no data directory"))?;
+    let file = to_file(&game.snapshot(), replay)?;
+    let text = toml::to_string(&file).map_err(|error| error.to_string())?;
+    write_atomically(&path, &text).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+//The file is gone once the game is loaded so a game can only be continued once.
+//A file that can not be loaded is moved aside, the message tells why.
+pub fn load() -> Option<Result<SavedGame, String>> {
+    let path = save_path()?;
+    let text = fs::read_to_string(&path).ok()?;
+    let saved_game = from_text(&text);
+    match &saved_game {
+        Ok(_) => {
+            let _ = fs::remove_file(&path);
+        }
+        Err(_) => {
+            let _ = fs::rename(&path, path.with_extension("toml.rejected"));
+        }
+    }
+    Some(saved_game)
+}
+
+fn from_text(text: &str) -> Result<SavedGame, String> {
+    let header: SaveHeader =
+        toml::from_str(text).map_err(|_| String::from("the file is damaged"))?;
+    if header.version < SAVE_VERSION {
+        return Err(String::from("it is from an older version"));
+    }
+    if header.version > SAVE_VERSION {
+        return Err(String::from("it is from a newer version"));
+    }
+    let file: SaveFile = toml::from_str(text).map_err(|_| String::from("the file is damaged"))?;
+    let mode =
+        GameMode::from_name(&file.mode).ok_or_else(|| format!("unknown mode '{}'", file.mode))?;
+    let randomizer = RandomizerKind::from_name(&file.randomizer)
+        .ok_or_else(|| format!("unknown randomizer '{}'", file.randomizer))?;
+    let rng_word_pos = file
+        .rng_position
+        .parse()
+        .map_err(|_| format!("bad generator position '{}'", file.rng_position))?;
+    let replay = Replay::from_file(file.replay)?;
+    let field_width = file.field.rows.first().map_or(0, |row| row.chars().count()) as i64;
+    let field_height = file.field.rows.len() as i64;
+    let in_range = COLUMNS.contains(&(field_width - WALLS_X as i64))
+        && ROWS.contains(&(field_height - WALLS_Y as i64))
+        && START_LEVEL.contains(&(file.start_level as i64));
+    if !in_range {
+        return Err(String::from("settings are out of range"));
+    }
+    check_timers(&file.timers, file.frames_played)?;
+    let snapshot = GameSnapshot {
+        field: parse_field(&file.field)?,
+        field_width: field_width as i16,
+        field_height: field_height as i16,
+        mode,
+        piece: TetrisShape {
+            current_piece: file.piece.kind,
+            current_rotation: parse_rotation(file.piece.rotation)?,
+            current_color: parse_saved_color(&file.piece.color)?,
+            current_x: file.piece.x,
+            current_y: file.piece.y,
+        },
+        piece_colors: parse_piece_colors(&file.piece_colors)?,
+        queue: file.queue,
+        randomizer,
+        randomizer_state: file.randomizer_state,
+        seed: file.seed,
+        rng_word_pos,
+        lines: file.timers.clearing_rows,
+        state: match file.timers.clearing_frames {
+            Some(frames) => GameState::ClearingLines(frames),
+            None => GameState::Falling,
+        },
+        line_clear_frames: file.timers.line_clear_frames,
+        score: file.score,
+        scoring: Scoring {
+            back_to_back: file.scoring.back_to_back,
+            combo: file.scoring.combo,
+        },
+        start_level: file.start_level,
+        level: file.level,
+        lines_cleared: file.lines_cleared,
+        gravity_progress: file.timers.gravity_progress,
+        pieces_placed: file.pieces_placed,
+        frames_played: file.frames_played,
+        last_kick: file.scoring.last_kick.map(|kick| Kick {
+            test: kick.test,
+            offset_x: kick.offset_x,
+            offset_y: kick.offset_y,
+        }),
+        lock_delay_ms: file.timers.lock_delay_ms,
+        auto_shift: AutoShift {
+            das_frames: file.timers.das_frames,
+            arr_frames: file.timers.arr_frames,
+            direction: file.timers.shift_direction.signum(),
+            timer: file.timers.shift_timer,
+            left_held: file.timers.left_held,
+            right_held: file.timers.right_held,
+        },
+        soft_drop_frames: file.timers.soft_drop_frames,
+        soft_drop_timer: file.timers.soft_drop_timer,
+        soft_drop_held: file.timers.soft_drop_held,
+        lock_timer: file.timers.lock_timer,
+        lock_resets: file.timers.lock_resets,
+        lowest_y: file.timers.lowest_y,
+        held_piece: file.hold,
+        can_hold: file.can_hold,
+        goal_reached: false,
+        game_over: false,
+    };
+    if snapshot.seed != replay.seed || snapshot.frames_played != replay.frames() {
+        return Err(String::from("the replay does not belong to the game"));
+    }
+    let game = Game::restore(build_tetromino(), snapshot)?;
+    Ok(SavedGame { game, replay })
+}
+
+//Delays are held to what the config allows and running timers to what they can reach,
+//a damaged value could otherwise overflow or keep a timer from ever running out
+fn check_timers(timers: &SavedTimers, frames_played: u64) -> Result<(), String> {
+    let most_frames = |range: RangeInclusive<i64>| ms_to_frames(*range.end() as u32);
+    let running = |timer: u32| timer as u64 <= frames_played;
+    let in_range = LOCK_DELAY_MS.contains(&(timers.lock_delay_ms as i64))
+        && timers.lock_timer <= most_frames(LOCK_DELAY_MS)
+        && timers.lock_resets <= MAX_LOCK_RESETS
+        && timers.line_clear_frames <= most_frames(LINE_CLEAR_DELAY_MS)
+        && timers
+            .clearing_frames
+            .is_none_or(|frames| frames <= timers.line_clear_frames.max(1))
+        && timers.das_frames <= most_frames(SHIFT_MS)
+        && timers.arr_frames <= most_frames(SHIFT_MS)
+        && (1..=most_frames(SOFT_DROP_MS)).contains(&timers.soft_drop_frames)
+        && timers.gravity_progress < ROW
+        && running(timers.shift_timer)
+        && running(timers.soft_drop_timer);
+    if !in_range {
+        return Err(String::from("timers are out of range"));
+    }
+    Ok(())
+}
+
+fn to_file(snapshot: &GameSnapshot, replay: &Replay) -> Result<SaveFile, String> {
+    let timers = SavedTimers {
+        clearing_frames: match snapshot.state {
+            GameState::ClearingLines(frames) => Some(frames),
+            GameState::Falling => None,
+        },
+        clearing_rows: snapshot.lines.clone(),
+        line_clear_frames: snapshot.line_clear_frames,
+        gravity_progress: snapshot.gravity_progress,
+        lock_delay_ms: snapshot.lock_delay_ms,
+        lock_timer: snapshot.lock_timer,
+        lock_resets: snapshot.lock_resets,
+        lowest_y: snapshot.lowest_y,
+        das_frames: snapshot.auto_shift.das_frames,
+        arr_frames: snapshot.auto_shift.arr_frames,
+        shift_direction: snapshot.auto_shift.direction,
+        shift_timer: snapshot.auto_shift.timer,
+        left_held: snapshot.auto_shift.left_held,
+        right_held: snapshot.auto_shift.right_held,
+        soft_drop_frames: snapshot.soft_drop_frames,
+        soft_drop_timer: snapshot.soft_drop_timer,
+        soft_drop_held: snapshot.soft_drop_held,
+    };
+    Ok(SaveFile {
+        version: SAVE_VERSION,
+        game_version: String::from(env!("CARGO_PKG_VERSION")),
+        date: high_scores::today(),
+        mode: String::from(snapshot.mode.name()),
+        seed: snapshot.seed,
+        rng_position: snapshot.rng_word_pos.to_string(),
+        randomizer: String::from(snapshot.randomizer.name()),
+        randomizer_state: snapshot.randomizer_state.clone(),
+        score: snapshot.score,
+        start_level: snapshot.start_level,
+        level: snapshot.level,
+        lines_cleared: snapshot.lines_cleared,
+        pieces_placed: snapshot.pieces_placed,
+        frames_played: snapshot.frames_played,
+        queue: snapshot.queue.clone(),
+        hold: snapshot.held_piece,
+        can_hold: snapshot.can_hold,
+        piece_colors: snapshot
+            .piece_colors
+            .iter()
+            .map(|color| color_name(*color))
+            .collect(),
+        piece: SavedPiece {
+            kind: snapshot.piece.current_piece,
+            x: snapshot.piece.current_x,
+            y: snapshot.piece.current_y,
+            rotation: rotation_degrees(&snapshot.piece.current_rotation),
+            color: color_name(snapshot.piece.current_color),
+        },
+        field: field_to_file(&snapshot.field)?,
+        timers,
+        scoring: SavedScoring {
+            back_to_back: snapshot.scoring.back_to_back,
+            combo: snapshot.scoring.combo,
+            last_kick: snapshot.last_kick.map(|kick| SavedKick {
+                test: kick.test,
+                offset_x: kick.offset_x,
+                offset_y: kick.offset_y,
+            }),
+        },
+        replay: replay.to_file(),
+    })
+}
+
+fn field_to_file(field: &Block) -> Result<SavedField, String> {
+    let mut colors: Vec<Color> = Vec::new();
+    let mut digit = |color: Color| -> Result<char, String> {
+        let index = match colors.iter().position(|other| *other == color) {
+            Some(index) => index,
+            None => {
+                colors.push(color);
+                colors.len() - 1
+            }
+        };
+        std::char::from_digit(index as u32, COLOR_DIGITS)
+            .ok_or_else(|| String::from("the field uses too many colors"))
+    };
+    let mut saved_field = SavedField {
+        colors: Vec::new(),
+        rows: Vec::new(),
+        foreground: Vec::new(),
+        background: Vec::new(),
+    };
+    for y in 0..field.get_height() {
+        let mut row = String::new();
+        let mut foreground = String::new();
+        let mut background = String::new();
+        for x in 0..field.get_width() {
+            row.push(field.get_part(x, y));
+            foreground.push(digit(field.get_foreground_color(x, y))?);
+            background.push(digit(field.get_background_color(x, y))?);
+        }
+        saved_field.rows.push(row);
+        saved_field.foreground.push(foreground);
+        saved_field.background.push(background);
+    }
+    saved_field.colors = colors.into_iter().map(color_name).collect();
+    Ok(saved_field)
+}
+
+fn parse_field(saved_field: &SavedField) -> Result<Block, String> {
+    let colors = saved_field
+        .colors
+        .iter()
+        .map(|name| parse_saved_color(name))
+        .collect::<Result<Vec<Color>, String>>()?;
+    let width = saved_field
+        .rows
+        .first()
+        .map_or(0, |row| row.chars().count());
+    let height = saved_field.rows.len();
+    if saved_field.foreground.len() != height || saved_field.background.len() != height {
+        return Err(String::from("the field is damaged"));
+    }
+    let mut field = Block::new(width as u16, height as u16);
+    for (y, row) in saved_field.rows.iter().enumerate() {
+        let foreground: Vec<char> = saved_field.foreground[y].chars().collect();
+        let background: Vec<char> = saved_field.background[y].chars().collect();
+        let cells: Vec<char> = row.chars().collect();
+        if cells.len() != width || foreground.len() != width || background.len() != width {
+            return Err(format!("row {} of the field is damaged", y));
+        }
+        let color = |digit: char| {
+            digit
+                .to_digit(COLOR_DIGITS)
+                .and_then(|index| colors.get(index as usize).copied())
+                .ok_or_else(|| format!("row {} of the field has an unknown color", y))
+        };
+        for x in 0..width {
+            field.change_content(
+                x as u16,
+                y as u16,
+                cells[x],
+                color(foreground[x])?,
+                color(background[x])?,
+            );
+        }
+    }
+    Ok(field)
+}
+
+fn parse_piece_colors(names: &[String]) -> Result<[Color; 7], String> {
+    let mut piece_colors = [Color::White; 7];
+    if names.len() != piece_colors.len() {
+        return Err(String::from("there have to be 7 piece colors"));
+    }
+    for (piece_color, name) in piece_colors.iter_mut().zip(names) {
+        *piece_color = parse_saved_color(name)?;
+    }
+    Ok(piece_colors)
+}
+
+fn parse_saved_color(name: &str) -> Result<Color, String> {
+    parse_color(name).ok_or_else(|| format!("unknown color '{}'", name))
+}
+
+fn rotation_degrees(rotation: &Rotation) -> u16 {
+    match rotation {
+        Rotation::R0 => 0,
+        Rotation::R90 => 90,
+        Rotation::R180 => 180,
+        Rotation::R270 => 270,
+    }
+}
+
+fn parse_rotation(degrees: u16) -> Result<Rotation, String> {
+    match degrees {
+        0 => Ok(Rotation::R0),
+        90 => Ok(Rotation::R90),
+        180 => Ok(Rotation::R180),
+        270 => Ok(Rotation::R270),
+        _ => Err(format!("bad rotation {}", degrees)),
+    }
+}
+
+fn save_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|directory| directory.join("termtris").join("save.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tetris::engine::{GameSettings, Input};
+
+    //A game a few pieces in with its replay, every piece in a different color
+    fn game_in_progress() -> (Game, Replay) {
+        let mut game_settings = GameSettings::new(14, 23);
+        game_settings.seed = u64::MAX;
+        game_settings.randomizer = RandomizerKind::Bag14;
+        game_settings.piece_colors[3] = Color::Rgb { r: 1, g: 2, b: 3 };
+        let mut game = Game::new(build_tetromino(), &game_settings);
+        let mut replay = Replay::new(&game_settings);
+        for frame in 0..400u64 {
+            let input = Input {
+                right: frame % 60 < 10,
+                rotate: frame % 45 == 7,
+                hold: frame == 100,
+                hard_drop: frame % 50 == 49,
+                ..Input::new()
+            };
+            replay.record(&input);
+            game.step(&input);
+        }
+        (game, replay)
+    }
+
+    fn save_text(game: &Game, replay: &Replay) -> String {
+        toml::to_string(&to_file(&game.snapshot(), replay).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn a_saved_game_loads_as_it_was() {
+        let (game, replay) = game_in_progress();
+        assert!(game.pieces_placed() > 0 && game.held_piece().is_some());
+        let saved_game = from_text(&save_text(&game, &replay)).unwrap();
+        assert_eq!(saved_game.game.snapshot(), game.snapshot());
+        assert_eq!(saved_game.replay.frames(), replay.frames());
+        let inputs = |replay: &Replay| -> Vec<u16> {
+            replay.inputs().map(|input| input.to_bits()).collect()
+        };
+        assert_eq!(inputs(&saved_game.replay), inputs(&replay));
+    }
+
+    #[test]
+    fn a_save_from_another_version_is_refused() {
+        let (game, replay) = game_in_progress();
+        let text = save_text(&game, &replay);
+        for version in [SAVE_VERSION - 1, SAVE_VERSION + 1] {
+            let other = text.replacen(
+                &format!("version = {}", SAVE_VERSION),
+                &format!("version = {}", version),
+                1,
+            );
+            assert!(from_text(&other).is_err());
+        }
+    }
+
+    #[test]
+    fn a_save_with_timers_out_of_range_is_refused() {
+        let (game, replay) = game_in_progress();
+        let damage: [fn(&mut SavedTimers); 4] = [
+            |timers| timers.lock_delay_ms = u32::MAX,
+            |timers| timers.das_frames = u32::MAX,
+            |timers| timers.arr_frames = u32::MAX,
+            |timers| timers.soft_drop_frames = 0,
+        ];
+        for damage in damage {
+            let mut file = to_file(&game.snapshot(), &replay).unwrap();
+            damage(&mut file.timers);
+            let text = toml::to_string(&file).unwrap();
+            assert_eq!(
+                from_text(&text).err(),
+                Some(String::from("timers are out of range"))
+            );
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crossterm::{event::KeyCode, style::Color};
+use std::io::Result;
+use std::ops::RangeInclusive;
+use tetris::engine::GameSettings;
+use tetris::engine::RandomizerKind;
+use tetris::engine::MAX_PREVIEW;
+use tetris::render::Frame;
+use tetris::render::FrameStyle;
+use tetris::render::Screen;
+use tetris::render::Text;
+
+use crate::config::Config;
+use crate::config::{LOCK_DELAY_MS, SHIFT_MS, SOFT_DROP_MS, START_LEVEL};
+use crate::controls::Controls;
+use crate::fit_layout;
+use crate::menu::Menu;
+use crate::ui;
+use crate::ScreenSetting;
+
+const SETTING_COUNT: usize = 8;
+const BACK: usize = SETTING_COUNT;
+
+//Left and right change the selected value, every change is written to the config file
+pub fn settings_screen(
+    screen: &mut Screen,
+    screen_settings: &mut ScreenSetting,
+    game_settings: &mut GameSettings,
+    config: &mut Config,
+    controls: &Controls,
+) -> Result<()> {
+    let frame = Frame::new(30, 13, FrameStyle::DoubleLine, Color::White, Color::Black);
+    let title = Text::new(String::from(" SETTINGS "), Color::Cyan, Color::Black);
+    let mut menu = Menu::new(vec![String::new(); SETTING_COUNT + 1]);
+    menu.set_entry(BACK, String::from("Back"));
+    let mut status: Option<(String, Color)> = None;
+    loop {
+        for index in 0..SETTING_COUNT {
+            menu.set_entry(index, setting_entry(index, screen_settings, game_settings));
+        }
+        ui::show(screen, screen_settings, |screen| {
+            screen.add_element_at(&frame, 2, 3);
+            screen.add_element_at(&title, 12, 3);
+            menu.render(screen, 4, 5);
+            let hint = String::from("Left/Right change value");
+            screen.add_string_at(hint, Color::DarkGrey, Color::Black, 4, 14);
+            if let Some((message, color)) = &status {
+                screen.add_string_at(message.clone(), *color, Color::Black, 2, 17);
+            }
+        })?;
+        let event = match ui::next_key_event(screen)? {
+            Some(event) => event,
+            None => continue,
+        };
+        let direction = match controls.menu_key(&event) {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Left => -1,
+            KeyCode::Right => 1,
+            _ if menu.handle(&event, controls) && menu.selected() == BACK => return Ok(()),
+            _ => continue,
+        };
+        if menu.selected() == BACK {
+            continue;
+        }
+        change_setting(menu.selected(), direction, screen_settings, game_settings);
+        store_setting(menu.selected(), config, screen_settings, game_settings);
+        status = Some(match config.save() {
+            Ok(()) => (String::from("Saved to config.toml"), Color::DarkGrey),
+            Err(message) => (message, Color::Red),
+        });
+    }
+}
+
+fn setting_entry(
+    index: usize,
+    screen_settings: &ScreenSetting,
+    game_settings: &GameSettings,
+) -> String {
+    let (name, value) = match index {
+        0 => (
+            "Ghost piece",
+            String::from(if screen_settings.show_ghost {
+                "on"
+            } else {
+                "off"
+            }),
+        ),
+        1 => ("Preview", game_settings.preview_count.to_string()),
+        2 => ("Randomizer", String::from(game_settings.randomizer.name())),
+        3 => ("Start level", game_settings.start_level.to_string()),
+        4 => ("Lock delay", format!("{}ms", game_settings.lock_delay_ms)),
+        5 => ("DAS", format!("{}ms", game_settings.das_ms)),
+        6 => ("ARR", format!("{}ms", game_settings.arr_ms)),
+        _ => ("Soft drop", format!("{}ms", game_settings.soft_drop_ms)),
+    };
+    format!("{:<14}{:>10}", name, value)
+}
+
+fn change_setting(
+    index: usize,
+    direction: i32,
+    screen_settings: &mut ScreenSetting,
+    game_settings: &mut GameSettings,
+) {
+    match index {
+        0 => screen_settings.show_ghost = !screen_settings.show_ghost,
+        1 => {
+            game_settings.preview_count = step(
+                game_settings.preview_count as u32,
+                direction,
+                1,
+                1..=MAX_PREVIEW as i64,
+            ) as usize;
+            fit_layout(screen_settings, game_settings);
+        }
+        2 => {
+            let kinds = RandomizerKind::ALL;
+            let current = kinds
+                .iter()
+                .position(|kind| *kind == game_settings.randomizer)
+                .unwrap_or(0);
+            let next = (current as i32 + direction).rem_euclid(kinds.len() as i32);
+            game_settings.randomizer = kinds[next as usize];
+        }
+        3 => game_settings.start_level = step(game_settings.start_level, direction, 1, START_LEVEL),
+        4 => {
+            game_settings.lock_delay_ms =
+                step(game_settings.lock_delay_ms, direction, 50, LOCK_DELAY_MS)
+        }
+        5 => game_settings.das_ms = step(game_settings.das_ms, direction, 10, SHIFT_MS),
+        6 => game_settings.arr_ms = step(game_settings.arr_ms, direction, 10, SHIFT_MS),
+        7 => {
+            game_settings.soft_drop_ms =
+                step(game_settings.soft_drop_ms, direction, 10, SOFT_DROP_MS)
+        }
+        _ => (),
+    }
+}
+
+//Only the value that was changed goes into the config, options given on the
+//command line stay out of the file
+fn store_setting(
+    index: usize,
+    config: &mut Config,
+    screen_settings: &ScreenSetting,
+    game_settings: &GameSettings,
+) {
+    match index {
+        0 => config.gameplay.ghost = screen_settings.show_ghost,
+        1 => config.gameplay.preview = game_settings.preview_count,
+        2 => config.gameplay.randomizer = String::from(game_settings.randomizer.name()),
+        3 => config.gameplay.start_level = game_settings.start_level,
+        4 => config.timing.lock_delay_ms = game_settings.lock_delay_ms,
+        5 => config.timing.das_ms = game_settings.das_ms,
+        6 => config.timing.arr_ms = game_settings.arr_ms,
+        7 => config.timing.soft_drop_ms = game_settings.soft_drop_ms,
+        _ => (),
+    }
+}
+
+//Kept inside the same range the config file and the command line accept
+fn step(value: u32, direction: i32, amount: u32, range: RangeInclusive<i64>) -> u32 {
+    let value = if direction < 0 {
+        (value as i64).saturating_sub(amount as i64)
+    } else {
+        (value as i64).saturating_add(amount as i64)
+    };
+    value.clamp(*range.start(), *range.end()) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stepped(index: usize, direction: i32, game_settings: &mut GameSettings) {
+        let mut screen_settings = ScreenSetting::new(34, 20);
+        change_setting(index, direction, &mut screen_settings, game_settings);
+    }
+
+    #[test]
+    fn a_value_at_the_top_of_its_range_is_not_lowered() {
+        let mut game_settings = GameSettings::new(12, 18);
+        game_settings.start_level = *START_LEVEL.end() as u32;
+        game_settings.lock_delay_ms = *LOCK_DELAY_MS.end() as u32;
+        game_settings.das_ms = *SHIFT_MS.end() as u32;
+        game_settings.arr_ms = *SHIFT_MS.end() as u32 - 5;
+        game_settings.soft_drop_ms = *SOFT_DROP_MS.end() as u32;
+        for index in 3..SETTING_COUNT {
+            stepped(index, 1, &mut game_settings);
+        }
+        assert_eq!(game_settings.start_level as i64, *START_LEVEL.end());
+        assert_eq!(game_settings.lock_delay_ms as i64, *LOCK_DELAY_MS.end());
+        assert_eq!(game_settings.das_ms as i64, *SHIFT_MS.end());
+        assert_eq!(game_settings.arr_ms as i64, *SHIFT_MS.end());
+        assert_eq!(game_settings.soft_drop_ms as i64, *SOFT_DROP_MS.end());
+    }
+
+    #[test]
+    fn values_above_the_old_limits_still_go_up() {
+        let mut game_settings = GameSettings::new(12, 18);
+        game_settings.start_level = 25;
+        game_settings.lock_delay_ms = 3000;
+        game_settings.das_ms = 800;
+        for index in 3..6 {
+            stepped(index, 1, &mut game_settings);
+        }
+        assert_eq!(game_settings.start_level, 26);
+        assert_eq!(game_settings.lock_delay_ms, 3050);
+        assert_eq!(game_settings.das_ms, 810);
+    }
+
+    #[test]
+    fn a_value_at_the_bottom_of_its_range_stays_there() {
+        let mut game_settings = GameSettings::new(12, 18);
+        game_settings.start_level = 1;
+        game_settings.das_ms = 0;
+        stepped(3, -1, &mut game_settings);
+        stepped(5, -1, &mut game_settings);
+        assert_eq!(game_settings.start_level, 1);
+        assert_eq!(game_settings.das_ms, 0);
+    }
+}
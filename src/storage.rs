@@ -0,0 +1,56 @@
+use std::fs;
+use std::io::{Result, Write};
+use std::path::Path;
+
+//Written to a temporary file first so a crash never leaves half a file behind
+pub fn write_atomically(path: &Path, text: &str) -> Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let mut temporary_name = path.as_os_str().to_owned();
+    temporary_name.push(".tmp");
+    let temporary_path = Path::new(&temporary_name);
+    let mut file = fs::File::create(temporary_path)?;
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(temporary_path, path)
+}
+
+//TOML integers are signed so seeds above i64::MAX are written as strings,
+//a seed written as a plain number still loads
+pub mod seed {
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::convert::TryFrom;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&seed.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        deserializer.deserialize_any(SeedVisitor)
+    }
+
+    struct SeedVisitor;
+
+    impl<'de> Visitor<'de> for SeedVisitor {
+        type Value = u64;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a seed as a number or a string")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<u64, E> {
+            value.parse().map_err(E::custom)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<u64, E> {
+            Ok(value)
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<u64, E> {
+            u64::try_from(value).map_err(E::custom)
+        }
+    }
+}
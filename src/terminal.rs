@@ -0,0 +1,137 @@
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{
+        DisableFocusChange, EnableFocusChange, KeyCode, KeyEvent, KeyModifiers,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, Clear, ClearType,
+        EnterAlternateScreen, LeaveAlternateScreen, SetSize, SetTitle,
+    },
+};
+use std::io::{stdout, Result};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+//Set while the terminal is taken over, restore only undoes it once
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+//Set while a game is played, an interrupt then lets the game save before leaving
+static SAVE_ON_INTERRUPT: AtomicBool = AtomicBool::new(false);
+//Exit code of an interrupt the running game still has to handle, 0 if there is none
+static INTERRUPT_CODE: AtomicI32 = AtomicI32::new(0);
+
+//Takes over the terminal and gives it back when dropped, after a panic or on a signal
+pub struct TerminalGuard {
+    keyboard_enhanced: bool,
+}
+
+impl TerminalGuard {
+    pub fn new(width: u16, height: u16) -> Result<TerminalGuard> {
+        install_panic_hook();
+        #[cfg(unix)]
+        listen_for_signals()?;
+        enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        //Only a request, the layout is centered in whatever size the terminal really has
+        execute!(
+            stdout(),
+            EnterAlternateScreen,
+            SetSize(width, height),
+            SetTitle("Tetris"),
+            EnableFocusChange,
+            Clear(ClearType::All),
+            Hide,
+        )?;
+        //Asks the terminal for press and release events where it supports them
+        let keyboard_enhanced = supports_keyboard_enhancement().unwrap_or(false);
+        if keyboard_enhanced {
+            execute!(
+                stdout(),
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                )
+            )?;
+            KEYBOARD_ENHANCED.store(true, Ordering::SeqCst);
+        }
+        Ok(TerminalGuard { keyboard_enhanced })
+    }
+
+    pub fn keyboard_enhanced(&self) -> bool {
+        self.keyboard_enhanced
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+//Best effort, there is nobody left to report a failure to
+pub fn restore() {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if KEYBOARD_ENHANCED.swap(false, Ordering::SeqCst) {
+        let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+    }
+    let _ = execute!(stdout(), DisableFocusChange, LeaveAlternateScreen, Show);
+    let _ = disable_raw_mode();
+}
+
+//Raw mode turns Ctrl+C into a key press instead of a signal
+pub fn is_interrupt(event: &KeyEvent) -> bool {
+    event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+//Leaves right away unless a game is being played, that game is saved first and then calls exit,
+//a second interrupt leaves even if the game has not got to it yet
+pub fn interrupt(code: i32) {
+    if !SAVE_ON_INTERRUPT.load(Ordering::SeqCst) || INTERRUPT_CODE.swap(code, Ordering::SeqCst) != 0
+    {
+        exit(code);
+    }
+}
+
+pub fn set_save_on_interrupt(save: bool) {
+    SAVE_ON_INTERRUPT.store(save, Ordering::SeqCst);
+}
+
+//The exit code of an interrupt that is waiting for the game to save
+pub fn interrupted() -> Option<i32> {
+    match INTERRUPT_CODE.load(Ordering::SeqCst) {
+        0 => None,
+        code => Some(code),
+    }
+}
+
+//Leaves right away from anywhere in the game
+pub fn exit(code: i32) -> ! {
+    restore();
+    std::process::exit(code)
+}
+
+//The message is printed after the terminal is back to normal so it can be read
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+    }));
+}
+
+#[cfg(unix)]
+fn listen_for_signals() -> Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGTERM, SIGHUP, SIGINT])?;
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            interrupt(128 + signal);
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use crossterm::{
+    event::{poll, read, Event, KeyCode, KeyEvent, KeyEventKind},
+    style::Color,
+};
+use std::io::Result;
+use std::time::Duration;
+use tetris::render::Screen;
+
+use crate::terminal;
+use crate::ScreenSetting;
+
+//Moves the layout to the middle of the terminal, false when it does not fit
+pub fn center_layout(screen: &mut Screen, screen_settings: &ScreenSetting) -> bool {
+    let layout_width = screen_settings.screen_width as u16;
+    let layout_height = screen_settings.screen_height as u16;
+    if screen.get_width() < layout_width || screen.get_height() < layout_height {
+        screen.set_origin(0, 0);
+        return false;
+    }
+    screen.set_origin(
+        (screen.get_width() - layout_width) / 2,
+        (screen.get_height() - layout_height) / 2,
+    );
+    true
+}
+
+pub fn render_too_small(screen: &mut Screen, screen_settings: &ScreenSetting) -> Result<()> {
+    screen.begin_render();
+    let message = String::from("Terminal too small");
+    screen.add_string_at(message, Color::Red, Color::Black, 0, 0);
+    let needed = format!(
+        "Need {}x{}, have {}x{}",
+        screen_settings.screen_width,
+        screen_settings.screen_height,
+        screen.get_width(),
+        screen.get_height()
+    );
+    screen.add_string_at(needed, Color::White, Color::Black, 0, 1);
+    screen.end_render()
+}
+
+//Draws one frame of a screen, or the too small message when the layout does not fit
+pub fn show<F: FnOnce(&mut Screen)>(
+    screen: &mut Screen,
+    screen_settings: &ScreenSetting,
+    draw: F,
+) -> Result<()> {
+    if !center_layout(screen, screen_settings) {
+        return render_too_small(screen, screen_settings);
+    }
+    screen.begin_render();
+    draw(screen);
+    screen.end_render()
+}
+
+//Waits for the next key press, a resize returns None so the caller can draw again
+pub fn next_key(screen: &mut Screen) -> Result<Option<KeyCode>> {
+    Ok(next_key_event(screen)?.map(|event| event.code))
+}
+
+//Like next_key but with the modifiers, for comparing against bindings,
+//an interrupt during a game returns None so the game can be saved
+pub fn next_key_event(screen: &mut Screen) -> Result<Option<KeyEvent>> {
+    loop {
+        if terminal::interrupted().is_some() {
+            return Ok(None);
+        }
+        //Wakes up now and then to notice a signal that came in while waiting
+        if !poll(Duration::from_millis(100))? {
+            continue;
+        }
+        match read()? {
+            Event::Key(event) if terminal::is_interrupt(&event) => terminal::interrupt(130),
+            Event::Key(event) if event.kind == KeyEventKind::Press => return Ok(Some(event)),
+            Event::Resize(width, height) => {
+                screen.resize(width, height);
+                return Ok(None);
+            }
+            _ => (),
+        }
+    }
+}